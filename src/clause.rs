@@ -1,6 +1,4 @@
-extern crate vec_map;
-
-use self::vec_map::VecMap;
+use std::mem;
 
 use literal::Literal;
 
@@ -13,99 +11,343 @@ pub enum WatchedUpdate {
     NoChange,
 }
 
-#[derive(Eq, PartialEq, Debug)]
-pub struct Clause(Vec<Literal>);
+/// The position of a clause in a `ClauseDatabase`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ClauseRef(u32);
+
+// Every clause starts with a header of `HEADER` words, followed by its
+// literals. The first word holds the number of literals shifted by two and the
+// `LEARNED` and `DELETED` flags.
+const HEADER: usize = 4;
+const SIZE: usize = 0;
+// smallest number of distinct decision levels seen among the literals
+const LBD: usize = 1;
+// how often the clause took part in recent conflicts, as the bits of an f32
+const ACTIVITY: usize = 2;
+// number of conflicts when the clause last took part in conflict analysis,
+// or its new position after garbage collection
+const LAST_USED: usize = 3;
+
+const LEARNED: u32 = 1;
+const DELETED: u32 = 2;
+// the size word leaves 30 bits for the number of literals
+const MAX_LEN: usize = (u32::MAX >> 2) as usize;
+
+/// Stores all clauses in a single contiguous vector, so that a clause is
+/// referenced by its position instead of a pointer.
+///
+/// Deleted clauses keep their space until `collect_garbage` compacts the
+/// database.
+#[derive(Debug, Default)]
+pub struct ClauseDatabase {
+    arena: Vec<Literal>,
+    // number of words taken up by deleted clauses
+    wasted: usize,
+}
+
+impl ClauseDatabase {
+    pub fn new() -> ClauseDatabase {
+        ClauseDatabase::default()
+    }
 
-impl Clause {
-    pub fn new(mut literals: Vec<i64>) -> Clause {
-        literals.sort_unstable();
-        literals.dedup();
-        Clause(literals.iter().map(Literal::new).collect())
+    /// Adds a clause that watches the first two of the given distinct
+    /// literals.
+    pub fn add(&mut self, literals: &[Literal], learned: bool) -> ClauseRef {
+        assert!(literals.len() <= MAX_LEN, "Clause too long");
+        assert!(
+            self.arena.len() <= u32::MAX as usize,
+            "Clause database too large"
+        );
+        let clause = ClauseRef(self.arena.len() as u32);
+        let flags = if learned { LEARNED } else { 0 };
+        self.arena.push(word((literals.len() as u32) << 2 | flags));
+        self.arena.push(word(0));
+        self.arena.push(word(0f32.to_bits()));
+        self.arena.push(word(0));
+        self.arena.extend_from_slice(literals);
+        clause
     }
 
-    pub fn from_literals(mut literals: Vec<Literal>) -> Clause {
-        literals.sort_unstable();
-        literals.dedup();
-        Clause(literals)
+    fn header(&self, clause: ClauseRef, field: usize) -> u32 {
+        self.arena[clause.0 as usize + field].index() as u32
     }
 
-    pub fn watched_literals(&self) -> (Literal, Literal) {
-        if self.0.len() == 1 {
-            (self.0[0], self.0[0])
-        } else {
-            (self.0[0], self.0[1])
-        }
+    fn set_header(&mut self, clause: ClauseRef, field: usize, value: u32) {
+        self.arena[clause.0 as usize + field] = word(value);
+    }
+
+    pub fn len(&self, clause: ClauseRef) -> usize {
+        (self.header(clause, SIZE) >> 2) as usize
+    }
+
+    pub fn is_learned(&self, clause: ClauseRef) -> bool {
+        self.header(clause, SIZE) & LEARNED != 0
+    }
+
+    pub fn set_learned(&mut self, clause: ClauseRef, learned: bool) {
+        let size = self.header(clause, SIZE) & !LEARNED;
+        self.set_header(clause, SIZE, if learned { size | LEARNED } else { size });
+    }
+
+    pub fn is_deleted(&self, clause: ClauseRef) -> bool {
+        self.header(clause, SIZE) & DELETED != 0
+    }
+
+    pub fn lbd(&self, clause: ClauseRef) -> usize {
+        self.header(clause, LBD) as usize
     }
 
-    pub fn literals(&self) -> &Vec<Literal> {
-        &self.0
+    pub fn set_lbd(&mut self, clause: ClauseRef, lbd: usize) {
+        self.set_header(clause, LBD, lbd as u32);
     }
 
-    pub fn propagate(&mut self, literal: &Literal, assigns: &VecMap<bool>) -> WatchedUpdate {
-        if !*literal == self.0[0] {
-            self.check(0, 1, &assigns)
+    pub fn activity(&self, clause: ClauseRef) -> f64 {
+        f64::from(f32::from_bits(self.header(clause, ACTIVITY)))
+    }
+
+    pub fn set_activity(&mut self, clause: ClauseRef, activity: f64) {
+        self.set_header(clause, ACTIVITY, (activity as f32).to_bits());
+    }
+
+    pub fn last_used(&self, clause: ClauseRef) -> usize {
+        self.header(clause, LAST_USED) as usize
+    }
+
+    pub fn set_last_used(&mut self, clause: ClauseRef, conflicts: usize) {
+        self.set_header(clause, LAST_USED, conflicts as u32);
+    }
+
+    pub fn literals(&self, clause: ClauseRef) -> &[Literal] {
+        let start = clause.0 as usize + HEADER;
+        &self.arena[start..start + self.len(clause)]
+    }
+
+    fn literals_mut(&mut self, clause: ClauseRef) -> &mut [Literal] {
+        let start = clause.0 as usize + HEADER;
+        let end = start + self.len(clause);
+        &mut self.arena[start..end]
+    }
+
+    /// Swaps two literals of a clause, which changes the watched literals if
+    /// one of them is among the first two.
+    pub fn swap(&mut self, clause: ClauseRef, a: usize, b: usize) {
+        self.literals_mut(clause).swap(a, b);
+    }
+
+    pub fn watched_literals(&self, clause: ClauseRef) -> (Literal, Literal) {
+        let literals = self.literals(clause);
+        if literals.len() == 1 {
+            (literals[0], literals[0])
         } else {
-            self.check(1, 0, &assigns)
+            (literals[0], literals[1])
         }
     }
 
-    fn check(&mut self, idx: usize, other_idx: usize, assigns: &VecMap<bool>) -> WatchedUpdate {
-        let lit = self.0[idx];
-        let val = assigns.get(lit.var());
-
-        if lit.satisfied_by(val) {
+    /// Moves the watched literal falsified by `literal` to the second
+    /// position and looks for a replacement for it. The first literal stays
+    /// watched.
+    pub fn propagate(
+        &mut self,
+        clause: ClauseRef,
+        literal: &Literal,
+        assigns: &[Option<bool>],
+    ) -> WatchedUpdate {
+        let literals = self.literals_mut(clause);
+        if !*literal == literals[0] {
+            literals.swap(0, 1);
+        }
+        let first = literals[0];
+        if first.satisfied_by(assigns[first.var()]) {
             return NoChange;
         }
 
         // Skipping the first two literals, return the index of the first literal that is not falsified under the current assignment.
-        let swap_with = self
-            .0
+        let swap_with = literals
             .iter()
             .enumerate()
             .skip(2)
-            .find(|(_, literal)| !literal.falsified_by(assigns.get(literal.var())))
+            .find(|(_, literal)| !literal.falsified_by(assigns[literal.var()]))
             .map(|(idx, _)| idx);
 
         match swap_with {
-            None => NowUnit(self.0[other_idx]),
+            None => NowUnit(first),
             Some(swap_idx) => {
-                self.0.swap(idx, swap_idx);
-                NewWatched(self.0[idx])
+                literals.swap(1, swap_idx);
+                NewWatched(literals[1])
+            }
+        }
+    }
+
+    /// Marks a clause as deleted. Its space is reclaimed by the next
+    /// `collect_garbage`.
+    pub fn delete(&mut self, clause: ClauseRef) {
+        debug_assert!(!self.is_deleted(clause), "Clause deleted twice");
+        let size = self.header(clause, SIZE);
+        self.set_header(clause, SIZE, size | DELETED);
+        self.wasted += HEADER + self.len(clause);
+    }
+
+    /// All clauses that are not deleted, in the order they were added.
+    pub fn iter(&self) -> Clauses<'_> {
+        Clauses {
+            database: self,
+            start: 0,
+        }
+    }
+
+    /// Whether deleted clauses take up more than a fifth of the database.
+    pub fn needs_collection(&self) -> bool {
+        self.wasted * 5 > self.arena.len()
+    }
+
+    /// Moves all clauses that are not deleted to the front of the database.
+    /// The returned `Relocation` maps references to the clauses to their new
+    /// positions.
+    pub fn collect_garbage(&mut self) -> Relocation {
+        let mut old = Vec::with_capacity(self.arena.len() - self.wasted);
+        mem::swap(&mut old, &mut self.arena);
+        let mut start = 0;
+        while start < old.len() {
+            let size = old[start + SIZE].index() as u32;
+            let end = start + HEADER + (size >> 2) as usize;
+            if size & DELETED == 0 {
+                let moved = self.arena.len();
+                self.arena.extend_from_slice(&old[start..end]);
+                old[start + LAST_USED] = word(moved as u32);
+            }
+            start = end;
+        }
+        self.wasted = 0;
+        Relocation { old }
+    }
+}
+
+/// Iterator over the clauses of a `ClauseDatabase`.
+pub struct Clauses<'a> {
+    database: &'a ClauseDatabase,
+    start: usize,
+}
+
+impl<'a> Iterator for Clauses<'a> {
+    type Item = ClauseRef;
+
+    fn next(&mut self) -> Option<ClauseRef> {
+        while self.start < self.database.arena.len() {
+            let clause = ClauseRef(self.start as u32);
+            self.start += HEADER + self.database.len(clause);
+            if !self.database.is_deleted(clause) {
+                return Some(clause);
             }
         }
+        None
     }
 }
 
+/// The positions of the clauses before and after a garbage collection.
+pub struct Relocation {
+    // the database before the collection, with the new position of every
+    // clause that was not deleted in its header
+    old: Vec<Literal>,
+}
+
+impl Relocation {
+    /// The new position of `clause`, or `None` if it was deleted.
+    pub fn get(&self, clause: ClauseRef) -> Option<ClauseRef> {
+        let start = clause.0 as usize;
+        if self.old[start + SIZE].index() as u32 & DELETED != 0 {
+            None
+        } else {
+            Some(ClauseRef(self.old[start + LAST_USED].index() as u32))
+        }
+    }
+}
+
+/// A header word stored in place of a literal.
+fn word(value: u32) -> Literal {
+    Literal::from_index(value as usize)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn literals(numbers: &[i64]) -> Vec<Literal> {
+        numbers.iter().map(Literal::new).collect()
+    }
+
     #[test]
     fn watched_literals_returns_unit() {
-        let literals = vec![-78634];
-        let clause = Clause::new(literals);
-        assert_eq!(Literal(-78634), clause.watched_literals().0);
-        assert_eq!(Literal(-78634), clause.watched_literals().1);
+        let mut clauses = ClauseDatabase::new();
+        let clause = clauses.add(&literals(&[-78634]), false);
+        assert_eq!(Literal::from(-78634), clauses.watched_literals(clause).0);
+        assert_eq!(Literal::from(-78634), clauses.watched_literals(clause).1);
     }
 
     #[test]
     fn watched_literals_returns_first_two() {
-        let literals = vec![-1, 3, 7];
-        let clause = Clause::new(literals);
-        assert_eq!(Literal(-1), clause.watched_literals().0);
-        assert_eq!(Literal(3), clause.watched_literals().1);
+        let mut clauses = ClauseDatabase::new();
+        let clause = clauses.add(&literals(&[-1, 3, 7]), false);
+        assert_eq!(Literal::from(-1), clauses.watched_literals(clause).0);
+        assert_eq!(Literal::from(3), clauses.watched_literals(clause).1);
     }
 
     #[test]
     fn propagate_swaps_literals_and_returns_new_watched() {
-        let literals = vec![-4, -2, 1, 3];
-        let mut clause = Clause::new(literals);
-        let assigns = VecMap::new();
-        let result = clause.propagate(&Literal(2), &assigns);
-        assert_eq!(
-            vec![Literal(-4), Literal(1), Literal(-2), Literal(3)],
-            clause.0
-        );
-        assert_eq!(NewWatched(Literal(1)), result);
+        let mut clauses = ClauseDatabase::new();
+        let clause = clauses.add(&literals(&[-4, -2, 1, 3]), false);
+        let assigns = vec![None; 5];
+        let result = clauses.propagate(clause, &Literal::from(2), &assigns);
+        assert_eq!(&literals(&[-4, 1, -2, 3])[..], clauses.literals(clause));
+        assert_eq!(NewWatched(Literal::from(1)), result);
+    }
+
+    #[test]
+    fn propagate_moves_false_literal_second_and_returns_unit() {
+        let mut clauses = ClauseDatabase::new();
+        let clause = clauses.add(&literals(&[-4, -2, 1]), false);
+        let mut assigns = vec![None; 5];
+        assigns[1] = Some(false);
+        assigns[4] = Some(true);
+        let result = clauses.propagate(clause, &Literal::from(4), &assigns);
+        assert_eq!(&literals(&[-2, -4, 1])[..], clauses.literals(clause));
+        assert_eq!(NowUnit(Literal::from(-2)), result);
+    }
+
+    #[test]
+    fn header_fields_are_stored_inline() {
+        let mut clauses = ClauseDatabase::new();
+        let clause = clauses.add(&literals(&[1, 2, 3]), true);
+        clauses.set_lbd(clause, 2);
+        clauses.set_activity(clause, 1.5);
+        clauses.set_last_used(clause, 42);
+        assert_eq!(3, clauses.len(clause));
+        assert!(clauses.is_learned(clause));
+        assert!(!clauses.is_deleted(clause));
+        assert_eq!(2, clauses.lbd(clause));
+        assert_eq!(1.5, clauses.activity(clause));
+        assert_eq!(42, clauses.last_used(clause));
+        assert_eq!(&literals(&[1, 2, 3])[..], clauses.literals(clause));
+    }
+
+    #[test]
+    fn collect_garbage_compacts_clauses() {
+        let mut clauses = ClauseDatabase::new();
+        let first = clauses.add(&literals(&[1, 2]), false);
+        let second = clauses.add(&literals(&[3, 4, 5]), true);
+        let third = clauses.add(&literals(&[-1, -2]), true);
+        clauses.set_lbd(third, 2);
+        clauses.delete(second);
+        assert!(clauses.needs_collection());
+
+        assert_eq!(vec![first, third], clauses.iter().collect::<Vec<_>>());
+
+        let relocation = clauses.collect_garbage();
+        assert_eq!(Some(first), relocation.get(first));
+        assert_eq!(None, relocation.get(second));
+        let third = relocation.get(third).expect("Clause was not deleted");
+        assert_eq!(&literals(&[-1, -2])[..], clauses.literals(third));
+        assert_eq!(2, clauses.lbd(third));
+        assert!(!clauses.needs_collection());
+        assert_eq!(2 * (HEADER + 2), clauses.arena.len());
     }
 }
@@ -1,14 +1,87 @@
 extern crate priority_queue;
 
 use std::cmp::Ordering;
+use std::fmt;
 
 use self::priority_queue::PriorityQueue;
 
 use literal::Literal;
 use solver::VariableName;
 
-#[derive(Debug, Eq, PartialEq)]
-struct VariablePriority(bool, usize, usize, usize);
+/// Chooses the decisions of the solver.
+///
+/// The solver reports every clause, assignment and conflict to its provider
+/// and asks it for the next literal to branch on whenever unit propagation
+/// reaches a fixpoint.
+pub trait DecisionProvider: fmt::Debug + Send {
+    /// Called for every clause added to the solver, original or learned.
+    fn new_clause(&mut self, literals: &[Literal]);
+
+    /// Called whenever a literal is put on the trail.
+    fn assign(&mut self, literal: Literal);
+
+    /// Called whenever backtracking removes a literal from the trail.
+    fn unassign(&mut self, literal: Literal);
+
+    /// Called once per conflict with the literals of every clause that took
+    /// part in its analysis.
+    fn conflict(&mut self, _literals: &[Literal]) {}
+
+    /// Called on every restart.
+    fn restart(&mut self) {}
+
+    /// Switches between the stable mode, in which decisions change slowly
+    /// and follow the longest assignment since the last restart, and the
+    /// focused mode, in which they follow the most recent conflicts. The
+    /// solver starts in focused mode.
+    ///
+    /// Providers without modes ignore this.
+    fn set_stable(&mut self, _stable: bool) {}
+
+    /// Makes the provider choose `value` whenever it decides `var`.
+    ///
+    /// Providers that do not support polarity hints ignore them.
+    fn set_polarity(&mut self, _var: VariableName, _value: bool) {}
+
+    /// Makes the provider decide `var` before every variable with a lower
+    /// priority. All variables start with priority 0.
+    ///
+    /// Providers that do not support priorities ignore them.
+    fn set_priority(&mut self, _var: VariableName, _priority: usize) {}
+
+    /// Allows or forbids decisions on `var`.
+    ///
+    /// Providers that do not support non-decision variables ignore this.
+    fn set_decision(&mut self, _var: VariableName, _decision: bool) {}
+
+    /// Returns whether `var` would be decided before `next` if both were
+    /// unassigned. The solver keeps the decisions that would be made again
+    /// on a restart.
+    ///
+    /// Providers that do not rank variables never keep any decision.
+    fn precedes(&self, _var: VariableName, _next: VariableName) -> bool {
+        false
+    }
+
+    /// Returns the next decision, or `None` if there is nothing left to
+    /// decide.
+    fn get_next(&mut self) -> Option<Literal>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct VariablePriority {
+    assigned: bool,
+    decision: bool,
+    priority: usize,
+    // the activity or bump time of the variable, depending on the mode
+    score: f64,
+    occurrences: usize,
+    positive: usize,
+    negative: usize,
+}
+
+// scores are never NaN
+impl Eq for VariablePriority {}
 
 impl PartialOrd for VariablePriority {
     fn partial_cmp(&self, other: &VariablePriority) -> Option<Ordering> {
@@ -18,89 +91,474 @@ impl PartialOrd for VariablePriority {
 
 impl Ord for VariablePriority {
     fn cmp(&self, other: &VariablePriority) -> Ordering {
-        if self.0 && !other.0 {
+        if !self.available() && other.available() {
             Ordering::Less
-        } else if !self.0 && other.0 {
+        } else if self.available() && !other.available() {
             Ordering::Greater
         } else {
-            self.1.cmp(&other.1)
+            self.priority
+                .cmp(&other.priority)
+                .then(
+                    self.score
+                        .partial_cmp(&other.score)
+                        .unwrap_or(Ordering::Equal),
+                )
+                .then(self.occurrences.cmp(&other.occurrences))
         }
     }
 }
 
 impl VariablePriority {
-    fn new(literal: &Literal) -> VariablePriority {
-        VariablePriority(
-            false,
-            1,
-            if literal.sign() { 1 } else { 0 },
-            if literal.sign() { 0 } else { 1 },
-        )
+    fn new() -> VariablePriority {
+        VariablePriority {
+            assigned: false,
+            decision: true,
+            priority: 0,
+            score: 0.0,
+            occurrences: 0,
+            positive: 0,
+            negative: 0,
+        }
     }
 
     fn occ(&self, literal: &Literal) -> VariablePriority {
-        VariablePriority(
-            self.0,
-            self.1 + 1,
-            self.2 + if literal.sign() { 1 } else { 0 },
-            self.3 + if literal.sign() { 0 } else { 1 },
-        )
+        VariablePriority {
+            occurrences: self.occurrences + 1,
+            positive: self.positive + if literal.sign() { 1 } else { 0 },
+            negative: self.negative + if literal.sign() { 0 } else { 1 },
+            ..self.clone()
+        }
     }
 
     fn set(&self) -> VariablePriority {
-        VariablePriority(true, self.1, self.2, self.3)
+        VariablePriority {
+            assigned: true,
+            ..self.clone()
+        }
     }
 
     fn unset(&self) -> VariablePriority {
-        VariablePriority(false, self.1, self.2, self.3)
+        VariablePriority {
+            assigned: false,
+            ..self.clone()
+        }
     }
 
-    fn literal(&self, vname: VariableName) -> Option<Literal> {
-        if self.0 {
-            None
-        } else {
-            Some(Literal(if self.2 > self.3 {
-                vname as i64
-            } else {
-                (vname as i64).wrapping_neg()
-            }))
+    fn with_priority(&self, priority: usize) -> VariablePriority {
+        VariablePriority {
+            priority,
+            ..self.clone()
+        }
+    }
+
+    fn with_score(&self, score: f64) -> VariablePriority {
+        VariablePriority {
+            score,
+            ..self.clone()
+        }
+    }
+
+    fn with_decision(&self, decision: bool) -> VariablePriority {
+        VariablePriority {
+            decision,
+            ..self.clone()
         }
     }
+
+    fn available(&self) -> bool {
+        !self.assigned && self.decision
+    }
+
+    fn majority(&self) -> bool {
+        self.positive > self.negative
+    }
 }
 
+/// How `DefaultDecisionProvider` chooses the value of a decision variable.
+pub enum Polarity {
+    /// The value the variable had when it was last unassigned, or the
+    /// polarity it occurs with most often if it was never assigned.
+    Saved,
+    /// The polarity the variable occurs with most often.
+    Occurrences,
+    /// Always false.
+    False,
+    /// Always true.
+    True,
+    /// A pseudo-random value drawn from a generator with the given seed.
+    Random(u64),
+    /// The polarity with the larger Jeroslow-Wang score, the sum of `2^-|C|`
+    /// over all clauses `C` the literal occurs in.
+    JeroslowWang,
+    /// The value returned by the given function.
+    Custom(Box<dyn Fn(VariableName) -> bool + Send>),
+}
+
+impl fmt::Debug for Polarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Polarity::Saved => write!(f, "Saved"),
+            Polarity::Occurrences => write!(f, "Occurrences"),
+            Polarity::False => write!(f, "False"),
+            Polarity::True => write!(f, "True"),
+            Polarity::Random(seed) => write!(f, "Random({})", seed),
+            Polarity::JeroslowWang => write!(f, "JeroslowWang"),
+            Polarity::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+/// Branches on the unassigned variable with the highest priority, choosing
+/// its value according to a `Polarity`. Among variables of equal priority, it
+/// prefers the one that took part in conflicts most recently in focused mode
+/// (VMTF), the one with the highest decaying conflict activity in stable mode
+/// (VSIDS), and otherwise the one that occurs in the most clauses. In stable
+/// mode, saved polarities give way to target phases, the values of the
+/// longest assignment since the last restart.
+///
+/// http://fmv.jku.at/papers/BiereFroehlich-SAT15.pdf
 #[derive(Debug)]
-pub struct DecisionProvider {
+pub struct DefaultDecisionProvider {
     queue: PriorityQueue<VariableName, VariablePriority>,
+    polarity: Polarity,
+    stable: bool,
+    // for each variable, its VSIDS activity
+    activity: Vec<f64>,
+    activity_inc: f64,
+    // for each variable, the conflict it was last bumped in
+    bumped: Vec<usize>,
+    conflicts: usize,
+    // for each variable, its current value
+    values: Vec<Option<bool>>,
+    assigned: usize,
+    // for each variable, its value in the longest assignment since the last restart
+    target: Vec<Option<bool>>,
+    target_assigned: usize,
+    // for each variable, its value when it was last unassigned
+    saved: Vec<Option<bool>>,
+    // for each variable, the value set by `set_polarity`
+    preferred: Vec<Option<bool>>,
+    // for each variable, the Jeroslow-Wang scores of its positive and negative literal
+    jeroslow_wang: Vec<(f64, f64)>,
+    random: u64,
 }
 
-impl DecisionProvider {
-    pub fn new() -> DecisionProvider {
-        DecisionProvider {
+impl DefaultDecisionProvider {
+    const ACTIVITY_DECAY: f64 = 0.95;
+    // activities are scaled down when the increment exceeds this
+    const ACTIVITY_LIMIT: f64 = 1e100;
+
+    pub fn new() -> DefaultDecisionProvider {
+        DefaultDecisionProvider::with_polarity(Polarity::Saved)
+    }
+
+    pub fn with_polarity(polarity: Polarity) -> DefaultDecisionProvider {
+        let random = match polarity {
+            // xorshift gets stuck at zero
+            Polarity::Random(seed) => seed.max(1),
+            _ => 1,
+        };
+        DefaultDecisionProvider {
             queue: PriorityQueue::new(),
+            polarity,
+            stable: false,
+            activity: Vec::new(),
+            activity_inc: 1.0,
+            bumped: Vec::new(),
+            conflicts: 0,
+            values: Vec::new(),
+            assigned: 0,
+            target: Vec::new(),
+            target_assigned: 0,
+            saved: Vec::new(),
+            preferred: Vec::new(),
+            jeroslow_wang: Vec::new(),
+            random,
+        }
+    }
+
+    fn ensure_var(&mut self, var: VariableName) {
+        if self.queue.get(&var).is_none() {
+            self.queue.push(var, VariablePriority::new());
+        }
+        self.ensure_tables(var);
+    }
+
+    fn ensure_tables(&mut self, var: VariableName) {
+        if self.saved.len() <= var {
+            self.activity.resize(var + 1, 0.0);
+            self.bumped.resize(var + 1, 0);
+            self.values.resize(var + 1, None);
+            self.target.resize(var + 1, None);
+            self.saved.resize(var + 1, None);
+            self.preferred.resize(var + 1, None);
+            self.jeroslow_wang.resize(var + 1, (0.0, 0.0));
+        }
+    }
+
+    fn sign(&mut self, var: VariableName, majority: bool) -> bool {
+        if let Some(value) = self.preferred[var] {
+            return value;
+        }
+        match self.polarity {
+            Polarity::Saved if self.stable => {
+                self.target[var].or(self.saved[var]).unwrap_or(majority)
+            }
+            Polarity::Saved => self.saved[var].unwrap_or(majority),
+            Polarity::Occurrences => majority,
+            Polarity::False => false,
+            Polarity::True => true,
+            Polarity::Random(_) => {
+                self.random ^= self.random << 13;
+                self.random ^= self.random >> 7;
+                self.random ^= self.random << 17;
+                self.random & 1 == 1
+            }
+            Polarity::JeroslowWang => {
+                let (pos, neg) = self.jeroslow_wang[var];
+                pos > neg
+            }
+            Polarity::Custom(ref polarity) => polarity(var),
         }
     }
 
-    pub fn new_clause(&mut self, literals: &Vec<Literal>) {
+    /// The score of `var` in the current mode.
+    fn score(&self, var: VariableName) -> f64 {
+        if self.stable {
+            self.activity[var]
+        } else {
+            self.bumped[var] as f64
+        }
+    }
+
+    fn update_scores(&mut self) {
+        for var in 0..self.activity.len() {
+            let score = self.score(var);
+            self.queue
+                .change_priority_by(&var, |prio| prio.with_score(score));
+        }
+    }
+}
+
+impl Default for DefaultDecisionProvider {
+    fn default() -> DefaultDecisionProvider {
+        DefaultDecisionProvider::new()
+    }
+}
+
+impl DecisionProvider for DefaultDecisionProvider {
+    fn new_clause(&mut self, literals: &[Literal]) {
+        for literal in literals {
+            self.ensure_var(literal.var());
+            self.queue
+                .change_priority_by(&literal.var(), |prio| prio.occ(literal));
+        }
+        if let Polarity::JeroslowWang = self.polarity {
+            let weight = 0.5f64.powi(literals.len() as i32);
+            for literal in literals {
+                let score = &mut self.jeroslow_wang[literal.var()];
+                if literal.sign() {
+                    score.0 += weight;
+                } else {
+                    score.1 += weight;
+                }
+            }
+        }
+    }
+
+    fn assign(&mut self, literal: Literal) {
+        self.ensure_tables(literal.var());
+        self.values[literal.var()] = Some(literal.sign());
+        self.assigned += 1;
+        self.queue
+            .change_priority_by(&literal.var(), |prio| prio.set());
+    }
+
+    fn unassign(&mut self, literal: Literal) {
+        self.ensure_tables(literal.var());
+        self.values[literal.var()] = None;
+        self.assigned -= 1;
+        self.saved[literal.var()] = Some(literal.sign());
+        self.queue
+            .change_priority_by(&literal.var(), |prio| prio.unset());
+    }
+
+    fn conflict(&mut self, literals: &[Literal]) {
+        if self.assigned > self.target_assigned {
+            self.target_assigned = self.assigned;
+            self.target.clone_from(&self.values);
+        }
+        self.conflicts += 1;
         for literal in literals {
-            if self.queue.get(&literal.var()).is_none() {
-                self.queue
-                    .push(literal.var(), VariablePriority::new(literal));
-            } else {
-                self.queue
-                    .change_priority_by(&literal.var(), |prio| prio.occ(literal))
+            let var = literal.var();
+            self.ensure_var(var);
+            if self.bumped[var] == self.conflicts {
+                continue;
+            }
+            self.bumped[var] = self.conflicts;
+            self.activity[var] += self.activity_inc;
+            let score = self.score(var);
+            self.queue
+                .change_priority_by(&var, |prio| prio.with_score(score));
+        }
+        self.activity_inc /= DefaultDecisionProvider::ACTIVITY_DECAY;
+        if self.activity_inc > DefaultDecisionProvider::ACTIVITY_LIMIT {
+            for activity in self.activity.iter_mut() {
+                *activity /= DefaultDecisionProvider::ACTIVITY_LIMIT;
+            }
+            self.activity_inc /= DefaultDecisionProvider::ACTIVITY_LIMIT;
+            if self.stable {
+                self.update_scores();
             }
         }
     }
 
-    pub fn get_next(&self) -> Option<Literal> {
-        self.queue.peek().and_then(|(i, prio)| prio.literal(*i))
+    fn restart(&mut self) {
+        self.target_assigned = 0;
+    }
+
+    fn set_stable(&mut self, stable: bool) {
+        if stable != self.stable {
+            self.stable = stable;
+            self.update_scores();
+        }
+    }
+
+    fn set_polarity(&mut self, var: VariableName, value: bool) {
+        self.ensure_var(var);
+        self.preferred[var] = Some(value);
+    }
+
+    fn set_priority(&mut self, var: VariableName, priority: usize) {
+        self.ensure_var(var);
+        self.queue
+            .change_priority_by(&var, |prio| prio.with_priority(priority));
+    }
+
+    fn set_decision(&mut self, var: VariableName, decision: bool) {
+        self.ensure_var(var);
+        self.queue
+            .change_priority_by(&var, |prio| prio.with_decision(decision));
+    }
+
+    fn precedes(&self, var: VariableName, next: VariableName) -> bool {
+        match (self.queue.get(&var), self.queue.get(&next)) {
+            // the order of `get_next` if both were unassigned
+            (Some((_, var)), Some((_, next))) => var.unset() >= next.unset(),
+            _ => false,
+        }
+    }
+
+    fn get_next(&mut self) -> Option<Literal> {
+        let (var, majority) = match self.queue.peek() {
+            Some((var, prio)) if prio.available() => (*var, prio.majority()),
+            _ => return None,
+        };
+        Some(Literal::from_var(var, self.sign(var, majority)))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_polarity_remembers_last_value() {
+        let mut provider = DefaultDecisionProvider::new();
+        provider.new_clause(&[Literal::from(1), Literal::from(2)]);
+        provider.new_clause(&[Literal::from(1), Literal::from(-2)]);
+        provider.new_clause(&[Literal::from(1), Literal::from(3)]);
+        assert_eq!(Some(Literal::from(1)), provider.get_next());
+        provider.assign(Literal::from(-1));
+        assert_eq!(Some(Literal::from(-2)), provider.get_next());
+        provider.unassign(Literal::from(-1));
+        assert_eq!(Some(Literal::from(-1)), provider.get_next());
+    }
+
+    #[test]
+    fn priority_overrides_occurrences() {
+        let mut provider = DefaultDecisionProvider::new();
+        provider.new_clause(&[Literal::from(1), Literal::from(2)]);
+        provider.new_clause(&[Literal::from(1), Literal::from(3)]);
+        provider.set_priority(3, 1);
+        provider.set_polarity(3, false);
+        assert_eq!(Some(Literal::from(-3)), provider.get_next());
+        provider.set_decision(3, false);
+        assert_eq!(Some(Literal::from(1)), provider.get_next());
+    }
+
+    #[test]
+    fn precedes_ignores_assignments() {
+        let mut provider = DefaultDecisionProvider::new();
+        provider.new_clause(&[Literal::from(1), Literal::from(2)]);
+        provider.new_clause(&[Literal::from(1), Literal::from(3)]);
+        provider.assign(Literal::from(1));
+        assert!(provider.precedes(1, 2));
+        assert!(!provider.precedes(2, 1));
+        provider.set_priority(2, 1);
+        assert!(provider.precedes(2, 1));
+    }
+
+    #[test]
+    fn precedes_follows_scores() {
+        let mut provider = DefaultDecisionProvider::new();
+        provider.new_clause(&[Literal::from(1), Literal::from(2)]);
+        provider.new_clause(&[Literal::from(1), Literal::from(3)]);
+        provider.conflict(&[Literal::from(1)]);
+        provider.conflict(&[Literal::from(2)]);
+        provider.assign(Literal::from(1));
+        // 1 occurs more often, but 2 was bumped more recently
+        assert!(!provider.precedes(1, 2));
+        assert!(provider.precedes(2, 1));
+    }
+
+    #[test]
+    fn conflicts_order_variables_by_mode() {
+        let mut provider = DefaultDecisionProvider::new();
+        provider.new_clause(&[Literal::from(1), Literal::from(2), Literal::from(3)]);
+        provider.new_clause(&[Literal::from(1), Literal::from(-2)]);
+        provider.new_clause(&[Literal::from(1), Literal::from(3)]);
+        provider.conflict(&[Literal::from(2)]);
+        provider.conflict(&[Literal::from(2), Literal::from(-2)]);
+        provider.conflict(&[Literal::from(3)]);
+        // the most recently bumped
+        assert_eq!(3, provider.get_next().unwrap().var());
+        provider.set_stable(true);
+        // the most active
+        assert_eq!(2, provider.get_next().unwrap().var());
+    }
+
+    #[test]
+    fn stable_mode_prefers_target_phases() {
+        let mut provider = DefaultDecisionProvider::new();
+        provider.new_clause(&[Literal::from(1), Literal::from(2)]);
+        provider.new_clause(&[Literal::from(1), Literal::from(-2)]);
+        provider.new_clause(&[Literal::from(-1)]);
+        provider.assign(Literal::from(-1));
+        provider.assign(Literal::from(2));
+        provider.conflict(&[]);
+        provider.unassign(Literal::from(2));
+        provider.unassign(Literal::from(-1));
+        provider.assign(Literal::from(1));
+        provider.conflict(&[]);
+        provider.unassign(Literal::from(1));
 
-    pub fn unset(&mut self, var: VariableName) {
-        self.queue.change_priority_by(&var, |prio| prio.unset());
+        assert_eq!(Some(Literal::from(1)), provider.get_next());
+        provider.set_stable(true);
+        assert_eq!(Some(Literal::from(-1)), provider.get_next());
+        provider.restart();
+        provider.assign(Literal::from(1));
+        provider.conflict(&[]);
+        provider.unassign(Literal::from(1));
+        assert_eq!(Some(Literal::from(1)), provider.get_next());
     }
 
-    pub fn set(&mut self, var: VariableName) {
-        self.queue.change_priority_by(&var, |prio| prio.set());
+    #[test]
+    fn jeroslow_wang_prefers_literals_in_short_clauses() {
+        let mut provider = DefaultDecisionProvider::with_polarity(Polarity::JeroslowWang);
+        provider.new_clause(&[Literal::from(1), Literal::from(2), Literal::from(3)]);
+        provider.new_clause(&[Literal::from(1), Literal::from(-2), Literal::from(3)]);
+        provider.new_clause(&[Literal::from(-1)]);
+        assert_eq!(Some(Literal::from(-1)), provider.get_next());
     }
 }
@@ -2,7 +2,9 @@
 extern crate log;
 
 mod clause;
-mod decision_provider;
-mod literal;
+pub mod decision_provider;
+pub mod literal;
 pub mod parser;
+mod reconstruction;
+pub mod restart;
 pub mod solver;
@@ -3,54 +3,105 @@ use std::ops::Not;
 
 use solver::VariableName;
 
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Literal(pub i64);
+/// A variable or its negation, encoded as `2 * var + 1` if negated and
+/// `2 * var` otherwise.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Literal(u32);
 
 impl Not for Literal {
     type Output = Literal;
     fn not(self) -> Literal {
-        Literal(self.0.wrapping_neg())
+        Literal(self.0 ^ 1)
     }
 }
 
 impl fmt::Debug for Literal {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", self.to_dimacs())
+    }
+}
+
+impl From<i64> for Literal {
+    fn from(num: i64) -> Literal {
+        Literal::new(&num)
     }
 }
 
 impl Literal {
+    /// The largest variable a literal can encode.
+    pub const MAX_VAR: VariableName = (u32::MAX >> 1) as VariableName;
+
+    /// Creates a literal from its DIMACS representation, a non-zero variable
+    /// number that is negative for negated variables.
     pub fn new(num: &i64) -> Literal {
-        Literal(*num)
+        Literal::from_var(num.unsigned_abs() as VariableName, num.is_positive())
     }
 
-    pub fn index(&self) -> usize {
+    pub fn from_var(var: VariableName, sign: bool) -> Literal {
+        assert!(var <= Literal::MAX_VAR, "Variable {} out of range", var);
+        Literal((var as u32) << 1 | if sign { 0 } else { 1 })
+    }
+
+    /// The literal with the given `index()`.
+    pub fn from_index(index: usize) -> Literal {
+        Literal(index as u32)
+    }
+
+    pub fn to_dimacs(&self) -> i64 {
         if self.sign() {
-            self.0 as usize * 2
+            self.var() as i64
         } else {
-            self.0.abs() as usize * 2 - 1
+            -(self.var() as i64)
         }
     }
 
+    /// A dense index for tables with an entry per literal.
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+
     pub fn sign(&self) -> bool {
-        self.0.is_positive()
+        self.0 & 1 == 0
     }
 
     pub fn var(&self) -> VariableName {
-        self.0.abs() as usize
+        (self.0 >> 1) as VariableName
     }
 
-    pub fn falsified_by(&self, var_val: Option<&bool>) -> bool {
+    pub fn falsified_by(&self, var_val: Option<bool>) -> bool {
         match var_val {
             None => false,
-            Some(val) => *val != self.sign(),
+            Some(val) => val != self.sign(),
         }
     }
 
-    pub fn satisfied_by(&self, var_val: Option<&bool>) -> bool {
+    pub fn satisfied_by(&self, var_val: Option<bool>) -> bool {
         match var_val {
             None => false,
-            Some(val) => *val == self.sign(),
+            Some(val) => val == self.sign(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_from_and_to_dimacs() {
+        for num in [1, -1, 2, -7, 123_456].iter() {
+            assert_eq!(*num, Literal::new(num).to_dimacs());
+        }
+        assert_eq!(Literal::from(-7), !Literal::from(7));
+        assert_eq!(7, Literal::from(-7).var());
+        assert_eq!(Literal::from_var(7, false), Literal::from(-7));
+        let max = Literal::MAX_VAR as i64;
+        assert_eq!(-max, Literal::from(-max).to_dimacs());
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_variables_out_of_range() {
+        Literal::from_var(Literal::MAX_VAR + 1, true);
+    }
+}
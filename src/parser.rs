@@ -1,10 +1,37 @@
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 use std::num::ParseIntError;
 
+use literal::Literal;
+
 #[derive(Debug, Clone)]
 pub struct Dimacs {
     pub clauses: DimacsClauses,
+    // the number of variables in the header
+    header_variables: usize,
+}
+
+impl Dimacs {
+    /// A formula without a header.
+    pub fn new(clauses: DimacsClauses) -> Dimacs {
+        Dimacs {
+            clauses,
+            header_variables: 0,
+        }
+    }
+
+    /// The number of variables given in the header, or the largest variable
+    /// in the clauses if that is larger.
+    pub fn variables(&self) -> usize {
+        self.clauses
+            .iter()
+            .flat_map(|clause| clause.iter())
+            .map(|literal| literal.unsigned_abs() as usize)
+            .max()
+            .unwrap_or(0)
+            .max(self.header_variables)
+    }
 }
 
 type DimacsClauses = Vec<Vec<i64>>;
@@ -12,10 +39,16 @@ type DimacsClauses = Vec<Vec<i64>>;
 #[derive(Debug)]
 pub struct DimacsError(&'static str);
 
+impl fmt::Display for DimacsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 pub fn parse_file(path: &str) -> Result<Dimacs, DimacsError> {
     if let Ok(mut file) = File::open(path) {
         let mut contents = String::new();
-        if let Ok(_) = file.read_to_string(&mut contents) {
+        if file.read_to_string(&mut contents).is_ok() {
             parse(contents.as_str())
         } else {
             Err(DimacsError("Could not read file"))
@@ -26,7 +59,15 @@ pub fn parse_file(path: &str) -> Result<Dimacs, DimacsError> {
 }
 
 pub fn parse(dimacs: &str) -> Result<Dimacs, DimacsError> {
-    dimacs
+    let header_variables = match dimacs
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| line.starts_with("p"))
+    {
+        Some(line) => parse_header(line)?,
+        None => 0,
+    };
+    let dimacs: DimacsClauses = dimacs
         .lines()
         .map(|line| line.trim())
         .filter(|line| {
@@ -37,18 +78,40 @@ pub fn parse(dimacs: &str) -> Result<Dimacs, DimacsError> {
                 && !line.is_empty()
         })
         .map(|line| {
-            line
-            .split_whitespace()
-            .map(|num| num.parse::<i64>())
-            // Keep all the errors so we know if something went wrong, but remove
-            // successfully parsed 0s which end each line in DIMACS format.
-            .filter(|num| match num {
-                Ok(x) => *x != 0,
-                Err(_) => true
-            })
-            .collect()
+            line.split_whitespace()
+                .map(|num| num.parse::<i64>())
+                // Keep all the errors so we know if something went wrong, but remove
+                // successfully parsed 0s which end each line in DIMACS format.
+                .filter(|num| match num {
+                    Ok(x) => *x != 0,
+                    Err(_) => true,
+                })
+                .collect()
         })
         .collect::<Result<DimacsClauses, ParseIntError>>()
-        .map(|clauses| Dimacs { clauses })
-        .map_err(|_| DimacsError("Could not parse"))
+        .map_err(|_| DimacsError("Could not parse"))?;
+    let dimacs = Dimacs {
+        clauses: dimacs,
+        header_variables,
+    };
+    if dimacs.variables() > Literal::MAX_VAR {
+        return Err(DimacsError("Variable out of range"));
+    }
+    Ok(dimacs)
+}
+
+/// The number of variables of a `p cnf <variables> <clauses>` header, which
+/// must be one that literals can encode.
+fn parse_header(line: &str) -> Result<usize, DimacsError> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let variables: usize = match fields.as_slice() {
+        ["p", "cnf", variables, ..] => variables
+            .parse()
+            .map_err(|_| DimacsError("Could not parse header"))?,
+        _ => return Err(DimacsError("Could not parse header")),
+    };
+    if variables > Literal::MAX_VAR {
+        return Err(DimacsError("Variable out of range"));
+    }
+    Ok(variables)
 }
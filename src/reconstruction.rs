@@ -0,0 +1,65 @@
+use literal::Literal;
+
+/// Remembers the clauses removed by preprocessing so that a model of the
+/// remaining clauses can be extended to a model of the original formula.
+///
+/// Every removed clause is stored with a witness literal of the clause.
+/// Going through the clauses in reverse order, the witness of every clause
+/// that is not satisfied is made true.
+#[derive(Debug, Default)]
+pub struct Reconstruction {
+    // witness literals with the index of their clause in `literals`
+    witnesses: Vec<(Literal, usize)>,
+    literals: Vec<Literal>,
+}
+
+impl Reconstruction {
+    pub fn new() -> Reconstruction {
+        Reconstruction::default()
+    }
+
+    pub fn push(&mut self, witness: Literal, clause: &[Literal]) {
+        self.witnesses.push((witness, self.literals.len()));
+        self.literals.extend_from_slice(clause);
+    }
+
+    /// Assigns the variables of the removed clauses in `model`, which is
+    /// indexed by variable, so that all removed clauses are satisfied.
+    pub fn extend(&self, model: &mut [Option<bool>]) {
+        let mut end = self.literals.len();
+        for &(witness, start) in self.witnesses.iter().rev() {
+            let satisfied = self.literals[start..end]
+                .iter()
+                .any(|literal| literal.satisfied_by(model[literal.var()]));
+            if !satisfied {
+                model[witness.var()] = Some(witness.sign());
+            }
+            end = start;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literals(numbers: &[i64]) -> Vec<Literal> {
+        numbers.iter().map(Literal::new).collect()
+    }
+
+    #[test]
+    fn extend_satisfies_removed_clauses() {
+        // 3 was eliminated from 1 2 3, -3 -1
+        let mut reconstruction = Reconstruction::new();
+        reconstruction.push(Literal::from(3), &literals(&[1, 2, 3]));
+        reconstruction.push(Literal::from(-3), &literals(&[-3]));
+
+        let mut model = vec![None, Some(false), Some(false), None];
+        reconstruction.extend(&mut model);
+        assert_eq!(Some(true), model[3]);
+
+        let mut model = vec![None, Some(true), Some(false), None];
+        reconstruction.extend(&mut model);
+        assert_eq!(Some(false), model[3]);
+    }
+}
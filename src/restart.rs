@@ -0,0 +1,305 @@
+use std::fmt;
+
+/// Decides when the solver restarts.
+///
+/// The solver reports every conflict to its policy and asks it before each
+/// decision whether it should backtrack to level 0 first.
+pub trait RestartPolicy: fmt::Debug + Send {
+    /// Called after every conflict with the LBD of the clause learned from it
+    /// and the number of literals that were assigned when it happened.
+    /// Returns `true` if the conflict blocked a restart.
+    fn conflict(&mut self, lbd: usize, trail_len: usize) -> bool;
+
+    /// Whether the solver should restart before its next decision.
+    fn should_restart(&self) -> bool;
+
+    /// Called after every restart.
+    fn restart(&mut self);
+}
+
+/// Restarts after `unit * luby(i)` conflicts, where `luby` is the sequence
+/// 1, 1, 2, 1, 1, 2, 4, 1, ...
+///
+/// http://www.cs.utexas.edu/~diz/Sub%20Websites/luby.pdf
+#[derive(Debug)]
+pub struct Luby {
+    unit: usize,
+    index: usize,
+    conflicts: usize,
+}
+
+impl Luby {
+    pub fn new(unit: usize) -> Luby {
+        Luby {
+            unit,
+            index: 0,
+            conflicts: 0,
+        }
+    }
+}
+
+impl RestartPolicy for Luby {
+    fn conflict(&mut self, _lbd: usize, _trail_len: usize) -> bool {
+        self.conflicts += 1;
+        false
+    }
+
+    fn should_restart(&self) -> bool {
+        self.conflicts >= self.unit * luby(self.index)
+    }
+
+    fn restart(&mut self) {
+        self.index += 1;
+        self.conflicts = 0;
+    }
+}
+
+/// Returns the `index`th element of the Luby sequence, starting at 0.
+fn luby(mut index: usize) -> usize {
+    // find the finite subsequence that contains index, and its size
+    let mut size = 1;
+    let mut seq = 0;
+    while size < index + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != index {
+        size = (size - 1) >> 1;
+        seq -= 1;
+        index %= size;
+    }
+    1 << seq
+}
+
+/// Restarts after `first * factor^i` conflicts.
+#[derive(Debug)]
+pub struct Geometric {
+    interval: f64,
+    factor: f64,
+    conflicts: usize,
+}
+
+impl Geometric {
+    pub fn new(first: usize, factor: f64) -> Geometric {
+        Geometric {
+            interval: first as f64,
+            factor,
+            conflicts: 0,
+        }
+    }
+}
+
+impl RestartPolicy for Geometric {
+    fn conflict(&mut self, _lbd: usize, _trail_len: usize) -> bool {
+        self.conflicts += 1;
+        false
+    }
+
+    fn should_restart(&self) -> bool {
+        self.conflicts as f64 >= self.interval
+    }
+
+    fn restart(&mut self) {
+        self.interval *= self.factor;
+        self.conflicts = 0;
+    }
+}
+
+/// Grows an inner geometric sequence of restart intervals until it reaches an
+/// outer one, then grows the outer sequence and starts the inner one over.
+///
+/// https://pdfs.semanticscholar.org/7ea4/cdd0003234f9e98ff5a080d9191c398e26c2.pdf
+#[derive(Debug)]
+pub struct InnerOuter {
+    first: f64,
+    factor: f64,
+    inner: f64,
+    outer: f64,
+    conflicts: usize,
+}
+
+impl InnerOuter {
+    pub fn new(first: usize, factor: f64) -> InnerOuter {
+        InnerOuter {
+            first: first as f64,
+            factor,
+            inner: first as f64,
+            outer: first as f64,
+            conflicts: 0,
+        }
+    }
+}
+
+impl Default for InnerOuter {
+    fn default() -> InnerOuter {
+        InnerOuter::new(100, 1.1)
+    }
+}
+
+impl RestartPolicy for InnerOuter {
+    fn conflict(&mut self, _lbd: usize, _trail_len: usize) -> bool {
+        self.conflicts += 1;
+        false
+    }
+
+    fn should_restart(&self) -> bool {
+        self.conflicts as f64 > self.inner
+    }
+
+    fn restart(&mut self) {
+        if self.inner >= self.outer {
+            self.outer *= self.factor;
+            self.inner = self.first;
+        } else {
+            self.inner *= self.factor;
+        }
+        self.conflicts = 0;
+    }
+}
+
+/// Exponential moving average that starts out as a plain average so the
+/// first values are not biased towards zero.
+#[derive(Debug)]
+struct Ema {
+    value: f64,
+    alpha: f64,
+    rate: f64,
+}
+
+impl Ema {
+    fn new(alpha: f64) -> Ema {
+        Ema {
+            value: 0.0,
+            alpha,
+            rate: 1.0,
+        }
+    }
+
+    fn update(&mut self, x: f64) {
+        self.value += self.rate * (x - self.value);
+        self.rate = (self.rate / 2.0).max(self.alpha);
+    }
+}
+
+/// Restarts when the recently learned clauses are worse than usual, that is
+/// when a fast moving average of their LBDs exceeds a slow one by `margin`.
+///
+/// If blocking is enabled, restarts are postponed while the trail is `block`
+/// times longer than on average, as the solver is then likely approaching a
+/// model.
+///
+/// https://www.ijcai.org/Proceedings/09/Papers/074.pdf
+/// http://fmv.jku.at/papers/BiereFroehlich-POS15.pdf
+#[derive(Debug)]
+pub struct Glucose {
+    margin: f64,
+    block: Option<f64>,
+    fast: Ema,
+    slow: Ema,
+    trail: Ema,
+    conflicts: usize,
+    total_conflicts: usize,
+}
+
+impl Glucose {
+    const MIN_CONFLICTS: usize = 50;
+    const BLOCK_AFTER: usize = 10_000;
+
+    pub fn new(margin: f64) -> Glucose {
+        Glucose {
+            margin,
+            block: None,
+            fast: Ema::new(1.0 / 32.0),
+            slow: Ema::new(1.0 / 16384.0),
+            trail: Ema::new(1.0 / 4096.0),
+            conflicts: 0,
+            total_conflicts: 0,
+        }
+    }
+
+    pub fn with_blocking(margin: f64, block: f64) -> Glucose {
+        Glucose {
+            block: Some(block),
+            ..Glucose::new(margin)
+        }
+    }
+}
+
+impl Default for Glucose {
+    fn default() -> Glucose {
+        Glucose::with_blocking(1.25, 1.4)
+    }
+}
+
+impl RestartPolicy for Glucose {
+    fn conflict(&mut self, lbd: usize, trail_len: usize) -> bool {
+        self.conflicts += 1;
+        self.total_conflicts += 1;
+        self.fast.update(lbd as f64);
+        self.slow.update(lbd as f64);
+
+        let mut blocked = false;
+        if let Some(block) = self.block {
+            if self.total_conflicts > Glucose::BLOCK_AFTER
+                && self.conflicts >= Glucose::MIN_CONFLICTS
+                && trail_len as f64 > block * self.trail.value
+            {
+                self.conflicts = 0;
+                blocked = true;
+            }
+        }
+        self.trail.update(trail_len as f64);
+        blocked
+    }
+
+    fn should_restart(&self) -> bool {
+        self.conflicts >= Glucose::MIN_CONFLICTS && self.fast.value > self.margin * self.slow.value
+    }
+
+    fn restart(&mut self) {
+        self.conflicts = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn luby_sequence() {
+        let sequence: Vec<usize> = (0..15).map(luby).collect();
+        assert_eq!(vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8], sequence);
+    }
+
+    #[test]
+    fn inner_outer_grows_from_one_conflict() {
+        let mut policy = InnerOuter::new(1, 1.1);
+        let mut intervals = Vec::new();
+        for _ in 0..60 {
+            let mut conflicts = 0;
+            while !policy.should_restart() {
+                policy.conflict(2, 10);
+                conflicts += 1;
+            }
+            intervals.push(conflicts);
+            policy.restart();
+        }
+        assert_eq!(2, intervals[0]);
+        assert!(intervals.iter().max().unwrap() > &intervals[0]);
+    }
+
+    #[test]
+    fn glucose_restarts_after_bad_clauses() {
+        let mut policy = Glucose::new(1.25);
+        for _ in 0..1000 {
+            policy.conflict(5, 100);
+        }
+        assert!(!policy.should_restart());
+        for _ in 0..Glucose::MIN_CONFLICTS {
+            policy.conflict(20, 100);
+        }
+        assert!(policy.should_restart());
+        policy.restart();
+        assert!(!policy.should_restart());
+    }
+}
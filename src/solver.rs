@@ -1,20 +1,31 @@
-extern crate vec_map;
-
-use std::cell::RefCell;
-use std::collections::VecDeque;
+use std::cmp::Ordering;
 use std::fmt;
-use std::rc::Rc;
+use std::mem;
 use std::time::Duration;
 use std::time::Instant;
 
-use self::vec_map::VecMap;
-
-use clause::{Clause, WatchedUpdate};
-use decision_provider::DecisionProvider;
+use clause::{ClauseDatabase, ClauseRef, WatchedUpdate};
+use decision_provider::{DecisionProvider, DefaultDecisionProvider};
 use literal::Literal;
 use parser::Dimacs;
+use reconstruction::Reconstruction;
+use restart::{InnerOuter, Luby, RestartPolicy};
+
+use self::inprocessing::{Schedule, Technique};
+
+// Simplifications of the formula, which run on decision level 0 after
+// propagation and return `Err(())` if they find it to be unsatisfiable.
+mod clause_elimination;
+mod elimination;
+mod hidden_elimination;
+mod inprocessing;
+mod probing;
+mod simplification;
+mod substitution;
+mod subsumption;
+mod variable_addition;
+mod vivification;
 
-type ClauseRef = Rc<RefCell<Clause>>;
 pub type VariableName = usize;
 
 #[derive(Debug, Eq, PartialEq)]
@@ -23,6 +34,30 @@ pub enum SolverResult {
     Unsat,
 }
 
+/// How the solver shrinks the clauses it learns from conflicts.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Minimization {
+    /// Learn first UIP clauses as they are.
+    None,
+    /// Remove literals implied by the other literals of the clause.
+    Recursive,
+    /// Like `Recursive`, then also remove literals implied by the asserting
+    /// literal through a binary clause.
+    RecursiveBinary,
+}
+
+/// Which clauses that are redundant for satisfiability are removed before
+/// the search.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ClauseElimination {
+    /// Keep all clauses.
+    None,
+    /// Remove blocked clauses.
+    Blocked,
+    /// Remove blocked clauses, also after adding covered literals.
+    Covered,
+}
+
 struct StatTime(Instant, Duration, usize);
 
 impl fmt::Debug for StatTime {
@@ -54,14 +89,53 @@ struct SolverStats {
     propagations: usize,
     learned_clauses: usize,
     learned_literals: usize,
+    minimized_literals: usize,
+    eliminated_variables: usize,
+    added_variables: usize,
+    blocked_clauses: usize,
+    failed_literals: usize,
+    hyper_binary_resolvents: usize,
+    substituted_variables: usize,
+    subsumed_clauses: usize,
+    vivified_clauses: usize,
+    vivified_literals: usize,
+    strengthened_clauses: usize,
+    hidden_tautologies: usize,
+    hidden_literals: usize,
+    inprocessing_propagations: usize,
+    probing_ticks: usize,
+    substitution_ticks: usize,
+    subsumption_ticks: usize,
+    vivification_ticks: usize,
+    hidden_elimination_ticks: usize,
     conflicts: usize,
     restarts: usize,
+    reused_trails: usize,
+    mode_switches: usize,
+    stable_conflicts: usize,
+    chronological_backtracks: usize,
+    blocked_restarts: usize,
+    reductions: usize,
+    deleted_clauses: usize,
+    promotions: usize,
+    core_clauses: usize,
+    tier2_clauses: usize,
+    local_clauses: usize,
+    collections: usize,
+    satisfied_clauses: usize,
+    falsified_literals: usize,
     init_time: StatTime,
     solve_time: StatTime,
     bcp_time: StatTime,
     sto_time: StatTime,
     ana_time: StatTime,
     bkt_time: StatTime,
+    simplification_time: StatTime,
+    probing_time: StatTime,
+    substitution_time: StatTime,
+    subsumption_time: StatTime,
+    vivification_time: StatTime,
+    hidden_elimination_time: StatTime,
 }
 
 impl SolverStats {
@@ -73,14 +147,53 @@ impl SolverStats {
             propagations: 0,
             learned_clauses: 0,
             learned_literals: 0,
+            minimized_literals: 0,
+            eliminated_variables: 0,
+            added_variables: 0,
+            blocked_clauses: 0,
+            failed_literals: 0,
+            hyper_binary_resolvents: 0,
+            substituted_variables: 0,
+            subsumed_clauses: 0,
+            vivified_clauses: 0,
+            vivified_literals: 0,
+            strengthened_clauses: 0,
+            hidden_tautologies: 0,
+            hidden_literals: 0,
+            inprocessing_propagations: 0,
+            probing_ticks: 0,
+            substitution_ticks: 0,
+            subsumption_ticks: 0,
+            vivification_ticks: 0,
+            hidden_elimination_ticks: 0,
             conflicts: 0,
             restarts: 0,
+            reused_trails: 0,
+            mode_switches: 0,
+            stable_conflicts: 0,
+            chronological_backtracks: 0,
+            blocked_restarts: 0,
+            reductions: 0,
+            deleted_clauses: 0,
+            promotions: 0,
+            core_clauses: 0,
+            tier2_clauses: 0,
+            local_clauses: 0,
+            collections: 0,
+            satisfied_clauses: 0,
+            falsified_literals: 0,
             init_time: StatTime::new(),
             solve_time: StatTime::new(),
             bcp_time: StatTime::new(),
             sto_time: StatTime::new(),
             ana_time: StatTime::new(),
             bkt_time: StatTime::new(),
+            simplification_time: StatTime::new(),
+            probing_time: StatTime::new(),
+            substitution_time: StatTime::new(),
+            subsumption_time: StatTime::new(),
+            vivification_time: StatTime::new(),
+            hidden_elimination_time: StatTime::new(),
         }
     }
 }
@@ -88,59 +201,193 @@ impl SolverStats {
 #[derive(Debug)]
 struct Conflict(ClauseRef);
 
+/// Why a variable was assigned by propagation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Reason {
+    Clause(ClauseRef),
+    /// A binary clause, with its literal other than the assigned one.
+    Binary(Literal),
+}
+
+/// An entry of a watch list.
+#[derive(Clone, Copy, Debug)]
+struct Watch {
+    clause: ClauseRef,
+    // some other literal of the clause, if it is true the clause is satisfied
+    // and does not need to be looked at; for binary clauses the other literal
+    blocker: Literal,
+}
+
+/// How long a learned clause is kept.
+#[derive(Debug, Eq, PartialEq)]
+enum Tier {
+    /// Kept forever.
+    Core,
+    /// Kept while it keeps taking part in conflicts.
+    Tier2,
+    /// Subject to activity based reduction.
+    Local,
+}
+
 #[derive(Debug)]
 pub struct Solver {
     trivially_unsat: bool,
     stats: SolverStats,
+    clauses: ClauseDatabase,
     learned_clauses: Vec<ClauseRef>,
-    bcp_queue: VecDeque<Literal>,
-    decision_provider: DecisionProvider,
-    restart: (usize, usize, usize),
+    decision_provider: Box<dyn DecisionProvider>,
+    restart_policy: Box<dyn RestartPolicy>,
+    // the restart policy of the mode the solver is not in
+    other_restart_policy: Box<dyn RestartPolicy>,
+    mode_switching: bool,
+    minimization: Minimization,
+    trail_reuse: bool,
+    chronological_backtracking: bool,
+    elimination: bool,
+    variable_addition: bool,
+    clause_elimination: ClauseElimination,
+    probing: bool,
+    substitution: bool,
+    subsumption: bool,
+    vivification: bool,
+    hidden_elimination: bool,
+    // number of literals on the trail when satisfied clauses and false
+    // literals were last removed
+    simplified_units: usize,
+    // for each inprocessing technique, when it runs next
+    schedules: Vec<Schedule>,
+    // clauses removed by preprocessing, to complete the model
+    reconstruction: Reconstruction,
+    // the model of the original formula after `solve` returned `SolverResult::Sat`
+    model: Vec<Option<bool>>,
+    // amount added to the activity of a learned clause that takes part in a conflict
+    clause_inc: f64,
+    // number of conflicts after which the learned clauses are reduced next
+    next_reduction: usize,
+    // whether the solver is in stable mode, as opposed to focused mode
+    stable: bool,
+    // number of conflicts after which the solver switches modes next
+    next_mode_switch: usize,
+    // number of conflicts between two mode switches, grows after each stable phase
+    mode_length: usize,
+    // for each decision level, the last `lbd_stamp` it was counted in an LBD computation
+    level_stamps: Vec<usize>,
+    lbd_stamp: usize,
 
     // for each variable, its value
-    assigns: VecMap<bool>,
+    assigns: Vec<Option<bool>>,
     // for each variable, the clause that implied the variable's value
-    reason: VecMap<ClauseRef>,
+    reason: Vec<Option<Reason>>,
     // for each variable, the decision level it was assigned at
-    level: VecMap<usize>,
+    level: Vec<usize>,
+    // for each variable, whether it is marked during conflict analysis
+    seen: Vec<bool>,
+    // for each variable, whether the user gave a hint for it, so that
+    // preprocessing must not remove it
+    frozen: Vec<bool>,
+    // for each variable, whether it was removed by variable elimination
+    eliminated: Vec<bool>,
 
     // assigned literals in chronological order
     trail: Vec<Literal>,
     // indices of trail separating the decision levels
     trail_lim: Vec<usize>,
+    // index of the first literal on the trail that is not propagated yet
+    qhead: usize,
+    // whether literals may be on the trail after literals of higher decision
+    // levels, since backtracking chronologically
+    out_of_order: bool,
 
     // indexed by literal.index(), a list of clauses that watch that literal
-    watches: VecMap<Vec<ClauseRef>>,
+    watches: Vec<Vec<Watch>>,
+    // indexed by literal.index(), the binary clauses that contain that literal
+    binary_watches: Vec<Vec<Watch>>,
 }
 
 impl Solver {
+    const FIRST_REDUCTION: usize = 2000;
+    const REDUCTION_INC: usize = 300;
+    const CLAUSE_DECAY: f64 = 0.999;
+    const CORE_LBD: usize = 2;
+    const TIER2_LBD: usize = 6;
+    const TIER2_UNUSED: usize = 10_000;
+    const BINARY_MINIMIZATION_SIZE: usize = 30;
+    const INPROCESSING_INTERVAL: usize = 10_000;
+    const CHRONOLOGICAL_LEVELS: usize = 100;
+    const MODE_CONFLICTS: usize = 1_000;
+    const MODE_GROWTH: usize = 2;
+    const STABLE_RESTART_UNIT: usize = 512;
+    const PROBING_PROPAGATIONS: usize = 1_000_000;
+    const SUBSUMPTION_STEPS: usize = 10_000_000;
+    const HIDDEN_ELIMINATION_STEPS: usize = 10_000_000;
+
     //
     // Initialisation
     //
 
-    fn new() -> Solver {
+    fn new(decision_provider: Box<dyn DecisionProvider>) -> Solver {
         Solver {
             trivially_unsat: false,
             stats: SolverStats::new(),
+            clauses: ClauseDatabase::new(),
             learned_clauses: Vec::new(),
-            bcp_queue: VecDeque::new(),
-            decision_provider: DecisionProvider::new(),
-            restart: (100, 100, 100),
+            decision_provider,
+            restart_policy: Box::new(InnerOuter::default()),
+            other_restart_policy: Box::new(Luby::new(Solver::STABLE_RESTART_UNIT)),
+            mode_switching: true,
+            minimization: Minimization::RecursiveBinary,
+            trail_reuse: true,
+            chronological_backtracking: true,
+            elimination: true,
+            variable_addition: true,
+            clause_elimination: ClauseElimination::Blocked,
+            probing: true,
+            substitution: true,
+            subsumption: true,
+            vivification: true,
+            hidden_elimination: true,
+            simplified_units: 0,
+            schedules: Technique::ALL.iter().map(|_| Schedule::new()).collect(),
+            reconstruction: Reconstruction::new(),
+            model: Vec::new(),
+            clause_inc: 1.0,
+            next_reduction: Solver::FIRST_REDUCTION,
+            stable: false,
+            next_mode_switch: Solver::MODE_CONFLICTS,
+            mode_length: Solver::MODE_CONFLICTS,
+            level_stamps: Vec::new(),
+            lbd_stamp: 0,
 
-            assigns: VecMap::new(),
-            reason: VecMap::new(),
-            level: VecMap::new(),
+            assigns: Vec::new(),
+            reason: Vec::new(),
+            level: Vec::new(),
+            seen: Vec::new(),
+            frozen: Vec::new(),
+            eliminated: Vec::new(),
 
             trail: Vec::new(),
             trail_lim: Vec::new(),
+            qhead: 0,
+            out_of_order: false,
 
-            watches: VecMap::new(),
+            watches: Vec::new(),
+            binary_watches: Vec::new(),
         }
     }
 
     pub fn from_dimacs(dimacs: &Dimacs) -> Solver {
-        let mut solver = Solver::new();
+        Solver::with_decision_provider(dimacs, Box::new(DefaultDecisionProvider::new()))
+    }
+
+    /// Creates a solver for the given formula that asks `decision_provider`
+    /// which literal to branch on next.
+    pub fn with_decision_provider(
+        dimacs: &Dimacs,
+        decision_provider: Box<dyn DecisionProvider>,
+    ) -> Solver {
+        let mut solver = Solver::new(decision_provider);
         solver.stats.init_time.start();
+        solver.ensure_vars(dimacs.variables());
         for literals in dimacs.clauses.iter() {
             solver.add_clause(literals.clone());
         }
@@ -148,7 +395,28 @@ impl Solver {
         solver
     }
 
-    fn add_clause(&mut self, literals: Vec<i64>) {
+    /// Makes room for the variables up to `variables` in all per-variable and
+    /// per-literal tables.
+    fn ensure_vars(&mut self, variables: usize) {
+        assert!(
+            variables <= Literal::MAX_VAR,
+            "Variable {} out of range",
+            variables
+        );
+        if self.assigns.len() > variables {
+            return;
+        }
+        self.assigns.resize(variables + 1, None);
+        self.reason.resize(variables + 1, None);
+        self.level.resize(variables + 1, 0);
+        self.seen.resize(variables + 1, false);
+        self.frozen.resize(variables + 1, false);
+        self.eliminated.resize(variables + 1, false);
+        self.watches.resize(2 * variables + 2, Vec::new());
+        self.binary_watches.resize(2 * variables + 2, Vec::new());
+    }
+
+    fn add_clause(&mut self, mut literals: Vec<i64>) {
         self.stats.clauses += 1;
 
         if self.trivially_unsat {
@@ -160,24 +428,147 @@ impl Solver {
             return;
         }
 
-        let clause = Clause::new(literals);
-        self.decision_provider.new_clause(&clause.literals());
-        let (wl1, wl2) = clause.watched_literals();
-        let clauseref = Rc::new(RefCell::new(clause));
-        self.watches
-            .entry(wl1.index())
-            .or_insert(Vec::new())
-            .push(clauseref.clone());
-        if wl1 != wl2 {
-            self.watches
-                .entry(wl2.index())
-                .or_insert(Vec::new())
-                .push(clauseref.clone());
-        } else if self.store_consequence(wl1, clauseref.clone()).is_err() {
+        literals.sort_unstable();
+        literals.dedup();
+        let literals: Vec<Literal> = literals.iter().map(Literal::new).collect();
+        let variables = literals.iter().map(|literal| literal.var()).max();
+        self.ensure_vars(variables.unwrap_or(0));
+        self.stats.literals += literals.len();
+        self.decision_provider.new_clause(&literals);
+        let clause = self.clauses.add(&literals, false);
+        self.attach_clause(clause);
+        let (wl1, wl2) = self.clauses.watched_literals(clause);
+        if wl1 == wl2 && self.store_consequence(wl1, Reason::Clause(clause)).is_err() {
             self.trivially_unsat = true;
         }
     }
 
+    //
+    // Hints
+    //
+
+    /// Makes the solver choose `value` whenever it decides `var`, for
+    /// example to start the search from the model of a similar formula.
+    pub fn set_polarity(&mut self, var: VariableName, value: bool) {
+        self.freeze(var);
+        self.decision_provider.set_polarity(var, value);
+    }
+
+    /// Makes the solver decide `var` before every variable with a lower
+    /// priority. All variables start with priority 0.
+    pub fn set_priority(&mut self, var: VariableName, priority: usize) {
+        self.freeze(var);
+        self.decision_provider.set_priority(var, priority);
+    }
+
+    /// Replaces the policy that decides when the solver restarts.
+    pub fn set_restart_policy(&mut self, restart_policy: Box<dyn RestartPolicy>) {
+        if self.stable {
+            self.other_restart_policy = restart_policy;
+        } else {
+            self.restart_policy = restart_policy;
+        }
+    }
+
+    /// Sets the restart policy of the stable mode, Luby restarts by default.
+    pub fn set_stable_restart_policy(&mut self, restart_policy: Box<dyn RestartPolicy>) {
+        if self.stable {
+            self.restart_policy = restart_policy;
+        } else {
+            self.other_restart_policy = restart_policy;
+        }
+    }
+
+    /// Enables or disables alternating between the focused mode, with the
+    /// restart policy set by `set_restart_policy`, and the stable mode, with
+    /// the one set by `set_stable_restart_policy`. The decision provider is
+    /// told about every switch. Without switching, the solver stays in
+    /// focused mode.
+    pub fn set_mode_switching(&mut self, mode_switching: bool) {
+        self.mode_switching = mode_switching;
+    }
+
+    /// Sets how learned clauses are shrunk.
+    pub fn set_minimization(&mut self, minimization: Minimization) {
+        self.minimization = minimization;
+    }
+
+    /// Enables or disables keeping the decisions on a restart that the
+    /// decision provider would make again.
+    pub fn set_trail_reuse(&mut self, trail_reuse: bool) {
+        self.trail_reuse = trail_reuse;
+    }
+
+    /// Enables or disables backtracking only one level instead of
+    /// backjumping over more than `CHRONOLOGICAL_LEVELS` levels after a
+    /// conflict.
+    pub fn set_chronological_backtracking(&mut self, chronological_backtracking: bool) {
+        self.chronological_backtracking = chronological_backtracking;
+    }
+
+    /// Allows or forbids decisions on `var`. A non-decision variable is only
+    /// ever assigned by propagation, so it is left out of the model if no
+    /// clause forces its value.
+    pub fn set_decision_var(&mut self, var: VariableName, decision: bool) {
+        self.freeze(var);
+        self.decision_provider.set_decision(var, decision);
+    }
+
+    /// Enables or disables bounded variable elimination before the search.
+    /// Variables with hints are never eliminated.
+    pub fn set_elimination(&mut self, elimination: bool) {
+        self.elimination = elimination;
+    }
+
+    /// Enables or disables bounded variable addition before the search. The
+    /// variables it adds are not part of the model.
+    pub fn set_variable_addition(&mut self, variable_addition: bool) {
+        self.variable_addition = variable_addition;
+    }
+
+    /// Sets which clauses that are redundant for satisfiability are removed
+    /// before the search. Variables with hints never block clauses.
+    pub fn set_clause_elimination(&mut self, clause_elimination: ClauseElimination) {
+        self.clause_elimination = clause_elimination;
+    }
+
+    /// Enables or disables failed literal probing with hyper-binary
+    /// resolution, before the search and after restarts.
+    pub fn set_probing(&mut self, probing: bool) {
+        self.probing = probing;
+    }
+
+    /// Enables or disables the substitution of equivalent literals, before
+    /// the search and after restarts. Variables with hints are never
+    /// substituted.
+    pub fn set_substitution(&mut self, substitution: bool) {
+        self.substitution = substitution;
+    }
+
+    /// Enables or disables the removal of subsumed clauses and
+    /// self-subsuming resolution, before the search and after restarts.
+    pub fn set_subsumption(&mut self, subsumption: bool) {
+        self.subsumption = subsumption;
+    }
+
+    /// Enables or disables shortening clauses by vivification after
+    /// restarts.
+    pub fn set_vivification(&mut self, vivification: bool) {
+        self.vivification = vivification;
+    }
+
+    /// Enables or disables hidden tautology and hidden literal elimination,
+    /// before the search and after restarts.
+    pub fn set_hidden_elimination(&mut self, hidden_elimination: bool) {
+        self.hidden_elimination = hidden_elimination;
+    }
+
+    fn freeze(&mut self, var: VariableName) {
+        assert!(var > 0, "Variable {} out of range", var);
+        self.ensure_vars(var);
+        self.frozen[var] = true;
+    }
+
     //
     // Main loop
     //
@@ -185,11 +576,30 @@ impl Solver {
     pub fn solve(&mut self) -> SolverResult {
         self.stats.solve_time.start();
         let result = self.internal_solve();
+        if result == SolverResult::Sat {
+            self.model = self.assigns.clone();
+            self.reconstruction.extend(&mut self.model);
+            // variables added by preprocessing come last
+            let variables = self.model.len() - self.stats.added_variables;
+            self.model.truncate(variables);
+        }
         self.stats.solve_time.end();
+        self.count_tiers();
+        info!("{:?}", self.restart_policy);
         info!("{:?}", self.stats);
         result
     }
 
+    /// Returns the assignment found by `solve` as DIMACS literals ordered by
+    /// variable. Only meaningful after `solve` returned `SolverResult::Sat`.
+    pub fn model(&self) -> Vec<i64> {
+        self.model
+            .iter()
+            .enumerate()
+            .filter_map(|(var, value)| value.map(|value| Literal::from_var(var, value).to_dimacs()))
+            .collect()
+    }
+
     fn internal_solve(&mut self) -> SolverResult {
         if self.trivially_unsat {
             debug!("Trivially unsat");
@@ -201,18 +611,107 @@ impl Solver {
             return SolverResult::Unsat;
         }
 
+        if self.simplify_clauses().is_err() {
+            debug!("Unsat by simplification");
+            return SolverResult::Unsat;
+        }
+
+        if self.probing
+            && self
+                .run_technique(Technique::Probing, Solver::PROBING_PROPAGATIONS)
+                .is_err()
+        {
+            debug!("Unsat by probing");
+            return SolverResult::Unsat;
+        }
+
+        if self.substitution && self.run_technique(Technique::Substitution, 0).is_err() {
+            debug!("Unsat by equivalent literal substitution");
+            return SolverResult::Unsat;
+        }
+
+        if self.subsumption
+            && self
+                .run_technique(Technique::Subsumption, Solver::SUBSUMPTION_STEPS)
+                .is_err()
+        {
+            debug!("Unsat by subsumption");
+            return SolverResult::Unsat;
+        }
+
+        if self.hidden_elimination
+            && self
+                .run_technique(
+                    Technique::HiddenElimination,
+                    Solver::HIDDEN_ELIMINATION_STEPS,
+                )
+                .is_err()
+        {
+            debug!("Unsat by hidden literal elimination");
+            return SolverResult::Unsat;
+        }
+
+        if self.clause_elimination != ClauseElimination::None {
+            self.eliminate_blocked_clauses();
+        }
+
+        if self.elimination && self.eliminate_variables().is_err() {
+            debug!("Unsat by variable elimination");
+            return SolverResult::Unsat;
+        }
+
+        if self.variable_addition && self.add_variables().is_err() {
+            debug!("Unsat by variable addition");
+            return SolverResult::Unsat;
+        }
+
         while let Some(decision) = self.decision_provider.get_next() {
             if self.should_restart() {
-                self.restart();
+                self.restart(decision);
+                if !self.trail_lim.is_empty() {
+                    continue;
+                }
+                // chronological backtracking can leave literals to propagate
+                if self.unit_propagate().is_some() {
+                    debug!("Unsat by propagation on level 0");
+                    return SolverResult::Unsat;
+                }
+                if self.simplify_clauses().is_err() || self.inprocess().is_err() {
+                    debug!("Unsat by inprocessing");
+                    return SolverResult::Unsat;
+                }
                 continue;
             }
+            if self.stats.conflicts >= self.next_reduction {
+                self.reduce_learned_clauses();
+            }
             self.store_decision(decision)
                 .expect("Decision lead to conflict");
             while let Some(conflict) = self.unit_propagate() {
                 self.stats.conflicts += 1;
-                if let Some((clause, unit, level)) = self.analyse_conflict(conflict) {
-                    self.backtrack(level);
-                    self.add_learned_clause(clause, unit).expect("Could not learn clause");
+                if self.stable {
+                    self.stats.stable_conflicts += 1;
+                }
+                if self.backtrack_to_conflict_level(&conflict) {
+                    continue;
+                }
+                if let Some((literals, unit, level)) = self.analyse_conflict(conflict) {
+                    let lbd = self.lbd(&literals);
+                    if self.restart_policy.conflict(lbd, self.trail.len()) {
+                        self.stats.blocked_restarts += 1;
+                    }
+                    let current_level = self.trail_lim.len();
+                    if self.chronological_backtracking
+                        && current_level - level > Solver::CHRONOLOGICAL_LEVELS
+                    {
+                        self.stats.chronological_backtracks += 1;
+                        self.out_of_order = true;
+                        self.backtrack(current_level - 1);
+                    } else {
+                        self.backtrack(level);
+                    }
+                    self.add_learned_clause(literals, lbd, unit)
+                        .expect("Could not learn clause");
                 } else {
                     return SolverResult::Unsat;
                 }
@@ -236,167 +735,422 @@ impl Solver {
     // Unit Propagation
     //
 
+    /// Propagates the literals on the trail that are not propagated yet,
+    /// binary clauses first.
+    ///
+    /// The watch list of each false literal is compacted in place: watches
+    /// whose blocker is true are kept without looking at the clause, and
+    /// watches that move to another literal are dropped.
     fn unit_propagate(&mut self) -> Option<Conflict> {
         trace!("BCP");
         self.stats.bcp_time.start();
         let mut result = None;
-        while let Some(unit) = self.bcp_queue.pop_front() {
+        while self.qhead < self.trail.len() && result.is_none() {
+            let unit = self.trail[self.qhead];
+            self.qhead += 1;
             self.stats.propagations += 1;
-            if let Some(clauses) = self.watches.get_mut((!unit).index()).map(|cs| cs.clone()) {
-                let mut to_remove = Vec::new();
-                for clause in clauses.iter() {
-                    debug!("propagate {:?} to {:?}", unit, clause);
-                    let update_result = clause.borrow_mut().propagate(&unit, &self.assigns);
-                    match update_result {
-                        WatchedUpdate::NoChange => {}
-                        WatchedUpdate::NowUnit(unit) => {
-                            let stored = self.store_consequence(unit, clause.clone());
-                            if stored.is_err() {
-                                self.bcp_queue.clear();
-                                result = Some(Conflict(clause.clone()));
-                                break;
+            result = self.propagate_binary(unit);
+            if result.is_some() {
+                self.qhead = self.trail.len();
+                break;
+            }
+            let mut watches = mem::take(&mut self.watches[(!unit).index()]);
+            let mut kept = 0;
+            let mut idx = 0;
+            while idx < watches.len() {
+                let watch = watches[idx];
+                idx += 1;
+                if watch
+                    .blocker
+                    .satisfied_by(self.assigns[watch.blocker.var()])
+                {
+                    watches[kept] = watch;
+                    kept += 1;
+                    continue;
+                }
+                debug!("propagate {:?} to {:?}", unit, watch.clause);
+                let update_result = self.clauses.propagate(watch.clause, &unit, &self.assigns);
+                let moved = Watch {
+                    clause: watch.clause,
+                    blocker: self.clauses.literals(watch.clause)[0],
+                };
+                match update_result {
+                    WatchedUpdate::NoChange => {
+                        watches[kept] = moved;
+                        kept += 1;
+                    }
+                    WatchedUpdate::NowUnit(unit) => {
+                        watches[kept] = moved;
+                        kept += 1;
+                        let unassigned = self.assigns[unit.var()].is_none();
+                        if self
+                            .store_consequence(unit, Reason::Clause(watch.clause))
+                            .is_err()
+                        {
+                            self.qhead = self.trail.len();
+                            result = Some(Conflict(watch.clause));
+                            while idx < watches.len() {
+                                watches[kept] = watches[idx];
+                                kept += 1;
+                                idx += 1;
                             }
-                        }
-                        WatchedUpdate::NewWatched(watched) => {
-                            self.watches
-                                .entry(watched.index())
-                                .or_insert(Vec::new())
-                                .push(clause.clone());
-                            to_remove.push(clause);
+                        } else if unassigned {
+                            self.update_lbd(watch.clause);
                         }
                     }
-                }
-                let mut clauses = self
-                    .watches
-                    .get_mut((!unit).index())
-                    .expect("Cannot get watching clauses to remove clauses");
-                for clause in to_remove {
-                    vec_remove(clauses, clause);
+                    WatchedUpdate::NewWatched(watched) => {
+                        self.watches[watched.index()].push(moved);
+                    }
                 }
             }
+            watches.truncate(kept);
+            self.watches[(!unit).index()] = watches;
         }
         self.stats.bcp_time.end();
         trace!("BCP done");
         result
     }
 
-    fn store_consequence(&mut self, literal: Literal, clause: ClauseRef) -> Result<(), ()> {
+    /// Assigns the other literal of every binary clause that is falsified by
+    /// `unit` apart from it.
+    fn propagate_binary(&mut self, unit: Literal) -> Option<Conflict> {
+        let watches = mem::take(&mut self.binary_watches[(!unit).index()]);
+        let mut result = None;
+        for watch in watches.iter() {
+            let other = watch.blocker;
+            match self.assigns[other.var()] {
+                Some(value) if value == other.sign() => {}
+                Some(_) => {
+                    result = Some(Conflict(watch.clause));
+                    break;
+                }
+                None => {
+                    self.store_consequence(other, Reason::Binary(!unit))
+                        .expect("Unassigned literal could not be assigned");
+                }
+            }
+        }
+        self.binary_watches[(!unit).index()] = watches;
+        result
+    }
+
+    /// Lowers the LBD of a learned clause that just became the reason for an
+    /// assignment if its literals now span fewer decision levels, which may
+    /// promote it to a better tier.
+    fn update_lbd(&mut self, clause: ClauseRef) {
+        let old_lbd = self.clauses.lbd(clause);
+        if !self.clauses.is_learned(clause) || old_lbd <= Solver::CORE_LBD {
+            return;
+        }
+        let lbd = Solver::count_levels(
+            &mut self.level_stamps,
+            &mut self.lbd_stamp,
+            &self.level,
+            self.trail_lim.len(),
+            self.clauses.literals(clause),
+        );
+        if lbd < old_lbd {
+            let old_tier = self.tier(clause);
+            self.clauses.set_lbd(clause, lbd);
+            if self.tier(clause) != old_tier {
+                self.stats.promotions += 1;
+            }
+        }
+    }
+
+    fn store_consequence(&mut self, literal: Literal, reason: Reason) -> Result<(), ()> {
         debug!("Store consequence {:?}", literal);
-        self.store_assignment(literal, Some(clause))
+        self.store_assignment(literal, Some(reason))
     }
 
     //
     // Conflict Analysis
     //
 
-    fn analyse_conflict(&mut self, conflict: Conflict) -> Option<(Clause, Literal, usize)> {
+    /// Backtracks to the highest decision level of the literals of the
+    /// conflict, which can be lower than the current one if the trail is out
+    /// of order. If only one literal of the conflict is on that level, the
+    /// conflict clause implies it one level lower and it is assigned there
+    /// without learning a clause; returns whether that was the case.
+    ///
+    /// https://doi.org/10.1007/978-3-030-24258-9_9
+    fn backtrack_to_conflict_level(&mut self, conflict: &Conflict) -> bool {
+        if !self.out_of_order {
+            return false;
+        }
+        let clause = conflict.0;
+        let literals = self.clauses.literals(clause);
+        let len = literals.len();
+        let conflict_level = literals
+            .iter()
+            .map(|literal| self.level[literal.var()])
+            .max()
+            .expect("Empty conflict clause");
+        let highest: Vec<usize> = (0..len)
+            .filter(|idx| self.level[literals[*idx].var()] == conflict_level)
+            .collect();
+        if conflict_level < self.trail_lim.len() {
+            self.backtrack(conflict_level);
+        }
+        if conflict_level == 0 || highest.len() > 1 {
+            return false;
+        }
+
+        // watch the implied literal and the highest of the others
+        self.backtrack(conflict_level - 1);
+        self.detach_clause(clause);
+        self.clauses.swap(clause, 0, highest[0]);
+        let second = (1..len)
+            .max_by_key(|idx| self.level[self.clauses.literals(clause)[*idx].var()])
+            .expect("Unit conflict clause above level 0");
+        self.clauses.swap(clause, 1, second);
+        self.attach_clause(clause);
+        let literals = self.clauses.literals(clause);
+        let reason = if literals.len() == 2 {
+            Reason::Binary(literals[1])
+        } else {
+            Reason::Clause(clause)
+        };
+        self.store_consequence(literals[0], reason)
+            .expect("Implied literal could not be assigned");
+        true
+    }
+
+    fn analyse_conflict(&mut self, conflict: Conflict) -> Option<(Vec<Literal>, Literal, usize)> {
         debug!("analyse {:?}", conflict);
-        if self.trail_lim.len() == 0 {
+        if self.trail_lim.is_empty() {
             return None;
         }
         self.stats.ana_time.start();
-        let (clause, unit) = self.get_clause_to_learn(conflict);
-        let lvl = self.get_backtrack_level(&clause);
-        let result = Some((clause, unit, lvl));
+        let mut literals = self.get_clause_to_learn(conflict);
+        let unit = literals[0];
+        let mut level = 0;
+        for idx in 1..literals.len() {
+            let literal_level = self.level[literals[idx].var()];
+            if literal_level > level {
+                level = literal_level;
+                literals.swap(1, idx);
+            }
+        }
+        let result = Some((literals, unit, level));
         self.stats.ana_time.end();
         result
     }
 
-    fn get_clause_to_learn(&mut self, conflict: Conflict) -> (Clause, Literal) {
-        let mut learned_literals: Vec<Literal> = conflict.0.borrow().literals().clone();
-        let current_literals = self.trail.split_at(*self.trail_lim.last().expect("No current literals")).1;
+    /// Derives the first UIP clause of the conflict by resolving it with the
+    /// reasons of its literals on the current decision level, walking the
+    /// trail backwards until only one such literal is left. That literal is
+    /// put first in the returned clause.
+    fn get_clause_to_learn(&mut self, conflict: Conflict) -> Vec<Literal> {
+        let current_level = self.trail_lim.len();
+        let mut learned_literals = vec![Literal::from_index(0)];
+        let mut participants = Vec::new();
+        // number of seen literals on the current decision level that are not resolved yet
+        let mut pending = 0;
+        let mut trail_idx = self.trail.len();
+        let mut reason = Reason::Clause(conflict.0);
+        let mut resolved: Option<Literal> = None;
         loop {
-            match self.select_resolution_literal(&learned_literals, current_literals) {
-                Err(non_unique) => {
-                    let antecedent = self
-                        .reason
-                        .get(non_unique.var())
-                        .expect("Cannot get reason of var for conflict analysis")
-                        .clone();
-                    Solver::resolve(
-                        &mut learned_literals,
-                        &antecedent.borrow().literals(),
-                        non_unique,
-                    );
+            let binary;
+            let literals = match reason {
+                Reason::Clause(clause) => {
+                    self.bump_clause(clause);
+                    self.clauses.literals(clause)
+                }
+                Reason::Binary(other) => {
+                    binary = [resolved.expect("Binary reason without literal"), other];
+                    &binary[..]
+                }
+            };
+            for literal in literals {
+                participants.push(*literal);
+                let var = literal.var();
+                if resolved.is_some_and(|resolved| resolved.var() == var) || self.seen[var] {
+                    continue;
                 }
-                Ok(unique) => {
-                    return (Clause::from_literals(learned_literals), unique);
+                let level = self.level[var];
+                if level == 0 {
+                    continue;
+                }
+                self.seen[var] = true;
+                if level == current_level {
+                    pending += 1;
+                } else {
+                    learned_literals.push(*literal);
+                }
+            }
+
+            // seen literals of lower levels can be out of order on the trail
+            let literal = loop {
+                trail_idx -= 1;
+                let var = self.trail[trail_idx].var();
+                if self.seen[var] && self.level[var] == current_level {
+                    break self.trail[trail_idx];
                 }
+            };
+            self.seen[literal.var()] = false;
+            pending -= 1;
+            if pending == 0 {
+                learned_literals[0] = !literal;
+                break;
             }
+            reason =
+                self.reason[literal.var()].expect("Cannot get reason of var for conflict analysis");
+            resolved = Some(literal);
         }
+        for literal in learned_literals.iter() {
+            self.seen[literal.var()] = false;
+        }
+
+        let unit = learned_literals[0];
+        self.minimize(&mut learned_literals, unit);
+        self.clause_inc /= Solver::CLAUSE_DECAY;
+        self.decision_provider.conflict(&participants);
+        learned_literals
     }
 
-    fn resolve(alits: &mut Vec<Literal>, blits: &Vec<Literal>, literal: Literal) {
-        alits.retain(|l| l.var() != literal.var());
-        for x in blits.iter() {
-            if x.var() != literal.var() {
-                alits.push(x.clone())
+    /// Removes literals from a learned clause that are implied by its other
+    /// literals, keeping the asserting literal `unit`.
+    ///
+    /// https://doi.org/10.1007/978-3-642-02777-2_23
+    fn minimize(&mut self, literals: &mut Vec<Literal>, unit: Literal) {
+        if self.minimization == Minimization::None {
+            return;
+        }
+        let before = literals.len();
+        let mut levels = 0;
+        for literal in literals.iter() {
+            self.seen[literal.var()] = true;
+            levels |= self.abstract_level(literal.var());
+        }
+
+        let mut marked = Vec::new();
+        let mut idx = 0;
+        while idx < literals.len() {
+            let literal = literals[idx];
+            if literal != unit
+                && self.reason[literal.var()].is_some()
+                && self.is_redundant(literal, levels, &mut marked)
+            {
+                marked.push(literal.var());
+                literals.swap_remove(idx);
+            } else {
+                idx += 1;
             }
         }
-        alits.sort_unstable();
-        alits.dedup();
+
+        if self.minimization == Minimization::RecursiveBinary
+            && literals.len() <= Solver::BINARY_MINIMIZATION_SIZE
+        {
+            self.minimize_binary(literals, unit);
+        }
+
+        for literal in literals.iter() {
+            self.seen[literal.var()] = false;
+        }
+        for var in marked {
+            self.seen[var] = false;
+        }
+        self.stats.minimized_literals += before - literals.len();
     }
 
-    fn select_resolution_literal(
-        &self,
-        learned_literals: &Vec<Literal>,
-        current_literals: &[Literal],
-    ) -> Result<Literal, Literal> {
-        let mut result = None;
-        for curlit in current_literals.iter().rev() {
-            for lealit in learned_literals.iter() {
-                if lealit.var() == curlit.var() {
-                    match result {
-                        None => result = Some(lealit),
-                        Some(rlit) => if self.reason.get(rlit.var()).is_none() {
-                            return Err(*lealit);
-                        } else {
-                            return Err(*rlit);
-                        },
+    /// Whether `literal` is implied by seen literals through the implication
+    /// graph. Variables found to be implied are marked as seen and added to
+    /// `marked`.
+    fn is_redundant(
+        &mut self,
+        literal: Literal,
+        levels: u64,
+        marked: &mut Vec<VariableName>,
+    ) -> bool {
+        let top = marked.len();
+        let mut stack = vec![literal];
+        while let Some(current) = stack.pop() {
+            let reason =
+                self.reason[current.var()].expect("Cannot get reason of redundant literal");
+            let binary;
+            let antecedents = match reason {
+                Reason::Clause(clause) => self.clauses.literals(clause),
+                Reason::Binary(other) => {
+                    binary = [other];
+                    &binary[..]
+                }
+            };
+            for antecedent in antecedents {
+                let var = antecedent.var();
+                if var == current.var() || self.seen[var] || self.level[var] == 0 {
+                    continue;
+                }
+                if self.reason[var].is_some() && self.abstract_level(var) & levels != 0 {
+                    self.seen[var] = true;
+                    stack.push(*antecedent);
+                    marked.push(var);
+                } else {
+                    for var in marked.drain(top..) {
+                        self.seen[var] = false;
                     }
+                    return false;
                 }
             }
         }
-        if let Some(literal) = result {
-            return Ok(*literal);
-        } else {
-            panic!("Clause does not contain any of the given variables");
+        true
+    }
+
+    /// Removes the literals whose negation is implied by the negation of the
+    /// asserting literal `unit` through a binary clause.
+    fn minimize_binary(&mut self, literals: &mut Vec<Literal>, unit: Literal) {
+        let mut implied = Vec::new();
+        for watch in self.binary_watches[unit.index()].iter() {
+            let other = watch.blocker;
+            if other.var() != unit.var()
+                && self.seen[other.var()]
+                && other.satisfied_by(self.assigns[other.var()])
+            {
+                implied.push(!other);
+            }
+        }
+        literals.retain(|literal| !implied.contains(literal));
+        for literal in implied {
+            self.seen[literal.var()] = false;
         }
     }
 
-    fn get_backtrack_level(&self, clause: &Clause) -> usize {
-        let current_dl = self.trail_lim.len();
-        let literals = clause.literals();
-        let mut dl = 0;
-        for literal in literals {
-            let vardl = *self.level.get(literal.var()).expect("No level for literal in learned clause");
-            if vardl == current_dl {
-                continue;
+    /// A bit set with one of 64 bits set depending on the decision level of
+    /// `var`, to cheaply rule out that two variables share a level.
+    fn abstract_level(&self, var: VariableName) -> u64 {
+        1 << (self.level[var] & 63)
+    }
+
+    fn bump_clause(&mut self, clause: ClauseRef) {
+        let activity = self.clauses.activity(clause) + self.clause_inc;
+        self.clauses.set_activity(clause, activity);
+        self.clauses.set_last_used(clause, self.stats.conflicts);
+        if activity > 1e20 {
+            for clause in self.learned_clauses.iter() {
+                let activity = self.clauses.activity(*clause);
+                self.clauses.set_activity(*clause, activity * 1e-20);
             }
-            dl = dl.max(vardl);
+            self.clause_inc *= 1e-20;
         }
-        dl
     }
 
-    fn add_learned_clause(&mut self, clause: Clause, unit: Literal) -> Result<(), ()> {
-        debug!("learning {:?} with unit {:?}", clause, unit);
+    fn add_learned_clause(
+        &mut self,
+        literals: Vec<Literal>,
+        lbd: usize,
+        unit: Literal,
+    ) -> Result<(), ()> {
+        debug!("learning {:?} with unit {:?}", literals, unit);
         self.stats.learned_clauses += 1;
-        self.stats.learned_literals += clause.literals().len();
-        self.decision_provider.new_clause(&clause.literals());
-        let (wl1, wl2) = clause.watched_literals();
-        let clauseref = Rc::new(RefCell::new(clause));
-        self.watches
-            .entry(wl1.index())
-            .or_insert(Vec::new())
-            .push(clauseref.clone());
-        if wl1 != wl2 {
-            self.watches
-                .entry(wl2.index())
-                .or_insert(Vec::new())
-                .push(clauseref.clone());
-        }
-        self.learned_clauses.push(clauseref.clone());
-        self.store_consequence(unit, clauseref)
+        self.stats.learned_literals += literals.len();
+        self.decision_provider.new_clause(&literals);
+        let clause = self.clauses.add(&literals, true);
+        self.clauses.set_lbd(clause, lbd);
+        self.clauses.set_activity(clause, self.clause_inc);
+        self.clauses.set_last_used(clause, self.stats.conflicts);
+        self.attach_clause(clause);
+        self.learned_clauses.push(clause);
+        self.store_consequence(unit, Reason::Clause(clause))
     }
 
     //
@@ -411,12 +1165,20 @@ impl Solver {
         );
         self.stats.bkt_time.start();
         let unset_list = self.trail.split_off(self.trail_lim[to_level]);
-        self.trail_lim.split_off(to_level);
+        self.trail_lim.truncate(to_level);
+        self.qhead = self.qhead.min(self.trail.len());
         for unset in unset_list {
-            self.assigns.remove(unset.var());
-            self.level.remove(unset.var());
-            self.reason.remove(unset.var());
-            self.decision_provider.unset(unset.var());
+            if self.out_of_order && self.level[unset.var()] <= to_level {
+                // assigned on a lower level, kept and propagated again
+                self.trail.push(unset);
+                continue;
+            }
+            self.assigns[unset.var()] = None;
+            self.reason[unset.var()] = None;
+            self.decision_provider.unassign(unset);
+        }
+        if to_level == 0 {
+            self.out_of_order = false;
         }
         self.stats.bkt_time.end();
     }
@@ -425,42 +1187,361 @@ impl Solver {
     // Restarts
     //
 
-    /// https://pdfs.semanticscholar.org/7ea4/cdd0003234f9e98ff5a080d9191c398e26c2.pdf
     fn should_restart(&mut self) -> bool {
-        if self.stats.conflicts > self.restart.2 && self.trail_lim.len() > 0 {
-            true
-        } else {
-            false
+        !self.trail_lim.is_empty()
+            && (self.mode_switch_due() || self.restart_policy.should_restart())
+    }
+
+    fn mode_switch_due(&self) -> bool {
+        self.mode_switching && self.stats.conflicts >= self.next_mode_switch
+    }
+
+    /// Switches between focused and stable mode. Each stable phase is as
+    /// long as the focused one before it, and both grow by `MODE_GROWTH`
+    /// after a stable phase.
+    fn switch_mode(&mut self) {
+        self.stable = !self.stable;
+        if !self.stable {
+            self.mode_length *= Solver::MODE_GROWTH;
         }
+        self.next_mode_switch = self.stats.conflicts + self.mode_length;
+        mem::swap(&mut self.restart_policy, &mut self.other_restart_policy);
+        self.decision_provider.set_stable(self.stable);
+        self.stats.mode_switches += 1;
     }
 
-    fn restart(&mut self) {
+    /// Backtracks to the highest level whose decision and the decisions
+    /// below it would all be made again before `next`, the decision the
+    /// search would continue with. Backtracks to level 0 if inprocessing is
+    /// due, which runs there, or the solver switches modes.
+    fn restart(&mut self, next: Literal) {
         trace!("{:?}", self.stats);
-        if self.restart.0 >= self.restart.1 {
-            self.restart.1 = (self.restart.1 as f64 * 1.1) as usize;
-            self.restart.0 = 100;
+        self.restart_policy.restart();
+        self.decision_provider.restart();
+        self.stats.restarts += 1;
+        let switch = self.mode_switch_due();
+        if switch {
+            self.switch_mode();
+        }
+        let level = if self.trail_reuse && !switch && !self.inprocessing_due() {
+            self.trail_lim
+                .iter()
+                .take_while(|start| {
+                    let decision = self.trail[**start];
+                    self.decision_provider.precedes(decision.var(), next.var())
+                })
+                .count()
         } else {
-            self.restart.0 = (self.restart.0 as f64 * 1.1) as usize;
+            0
+        };
+        if level > 0 {
+            self.stats.reused_trails += 1;
+        }
+        if level < self.trail_lim.len() {
+            self.backtrack(level);
+        }
+    }
+
+    //
+    // Learned clause reduction
+    //
+
+    /// Deletes learned clauses that are unlikely to be useful again.
+    ///
+    /// Clauses are classified by their LBD: core clauses are kept forever,
+    /// tier2 clauses as long as they keep taking part in conflicts, and the
+    /// less active half of the remaining local clauses is deleted. Clauses
+    /// that are the reason for an assignment are always kept. The clause
+    /// database is compacted once enough clauses have been deleted.
+    ///
+    /// https://www.ijcai.org/Proceedings/09/Papers/074.pdf
+    /// https://doi.org/10.1007/978-3-319-24318-4_23
+    fn reduce_learned_clauses(&mut self) {
+        self.stats.reductions += 1;
+        self.next_reduction = self.stats.conflicts
+            + Solver::FIRST_REDUCTION
+            + Solver::REDUCTION_INC * self.stats.reductions;
+
+        let (mut local, kept): (Vec<ClauseRef>, Vec<ClauseRef>) =
+            mem::take(&mut self.learned_clauses)
+                .into_iter()
+                .partition(|clause| self.tier(*clause) == Tier::Local);
+        self.learned_clauses = kept;
+        local.sort_by(|a, b| {
+            self.clauses
+                .activity(*a)
+                .partial_cmp(&self.clauses.activity(*b))
+                .unwrap_or(Ordering::Equal)
+        });
+        let limit = local.len() / 2;
+        for (idx, clause) in local.into_iter().enumerate() {
+            if idx < limit && !self.is_locked(clause) {
+                self.detach_clause(clause);
+                self.clauses.delete(clause);
+                self.stats.deleted_clauses += 1;
+            } else {
+                self.learned_clauses.push(clause);
+            }
+        }
+        if self.clauses.needs_collection() {
+            self.collect_garbage();
+        }
+        self.count_tiers();
+    }
+
+    /// Compacts the clause database and updates all references to clauses.
+    fn collect_garbage(&mut self) {
+        self.stats.collections += 1;
+        let relocation = self.clauses.collect_garbage();
+        for watches in self
+            .watches
+            .iter_mut()
+            .chain(self.binary_watches.iter_mut())
+        {
+            *watches = watches
+                .iter()
+                .filter_map(|watch| {
+                    relocation.get(watch.clause).map(|clause| Watch {
+                        clause,
+                        blocker: watch.blocker,
+                    })
+                })
+                .collect();
+        }
+        for reason in self.reason.iter_mut() {
+            if let Some(Reason::Clause(clause)) = *reason {
+                *reason = Some(Reason::Clause(
+                    relocation.get(clause).expect("Reason was deleted"),
+                ));
+            }
+        }
+        self.learned_clauses = self
+            .learned_clauses
+            .iter()
+            .filter_map(|clause| relocation.get(*clause))
+            .collect();
+    }
+
+    fn tier(&self, clause: ClauseRef) -> Tier {
+        let lbd = self.clauses.lbd(clause);
+        if lbd <= Solver::CORE_LBD {
+            Tier::Core
+        } else if lbd <= Solver::TIER2_LBD
+            && self.stats.conflicts - self.clauses.last_used(clause) < Solver::TIER2_UNUSED
+        {
+            Tier::Tier2
+        } else {
+            Tier::Local
+        }
+    }
+
+    fn count_tiers(&mut self) {
+        let (mut core, mut tier2, mut local) = (0, 0, 0);
+        for clause in self.learned_clauses.iter() {
+            match self.tier(*clause) {
+                Tier::Core => core += 1,
+                Tier::Tier2 => tier2 += 1,
+                Tier::Local => local += 1,
+            }
+        }
+        self.stats.core_clauses = core;
+        self.stats.tier2_clauses = tier2;
+        self.stats.local_clauses = local;
+    }
+
+    fn is_locked(&self, clause: ClauseRef) -> bool {
+        let (wl1, wl2) = self.clauses.watched_literals(clause);
+        [wl1, wl2]
+            .iter()
+            .any(|literal| self.reason[literal.var()] == Some(Reason::Clause(clause)))
+    }
+
+    fn attach_clause(&mut self, clause: ClauseRef) {
+        let (wl1, wl2) = self.clauses.watched_literals(clause);
+        let watches = if self.clauses.len(clause) == 2 {
+            &mut self.binary_watches
+        } else {
+            &mut self.watches
+        };
+        watches[wl1.index()].push(Watch {
+            clause,
+            blocker: wl2,
+        });
+        if wl1 != wl2 {
+            watches[wl2.index()].push(Watch {
+                clause,
+                blocker: wl1,
+            });
+        }
+    }
+
+    /// Adds a clause that is implied by the formula on decision level 0,
+    /// without its literals that are false. Returns `None` if the clause is
+    /// satisfied and fails if it is falsified. A unit clause is propagated.
+    fn add_derived_clause(
+        &mut self,
+        literals: Vec<Literal>,
+        learned: bool,
+    ) -> Result<Option<ClauseRef>, ()> {
+        let clause = self.store_derived_clause(literals, learned)?;
+        if let Some(clause) = clause {
+            self.decision_provider
+                .new_clause(self.clauses.literals(clause));
+        }
+        Ok(clause)
+    }
+
+    /// Like `add_derived_clause`, without reporting the clause to the
+    /// decision provider.
+    fn store_derived_clause(
+        &mut self,
+        mut literals: Vec<Literal>,
+        learned: bool,
+    ) -> Result<Option<ClauseRef>, ()> {
+        if literals
+            .iter()
+            .any(|literal| literal.satisfied_by(self.assigns[literal.var()]))
+        {
+            return Ok(None);
+        }
+        literals.retain(|literal| !literal.falsified_by(self.assigns[literal.var()]));
+        if literals.is_empty() {
+            return Err(());
+        }
+        let clause = self.clauses.add(&literals, learned);
+        self.attach_clause(clause);
+        if literals.len() == 1 {
+            self.store_consequence(literals[0], Reason::Clause(clause))?;
+            if self.unit_propagate().is_some() {
+                return Err(());
+            }
+        }
+        Ok(Some(clause))
+    }
+
+    /// Replaces `clause` by a clause with the given literals that is implied
+    /// by the formula on decision level 0. A learned clause keeps its header.
+    /// The replacement is not reported to the decision provider, which has
+    /// already counted the occurrences of the replaced clause.
+    fn replace_clause(
+        &mut self,
+        clause: ClauseRef,
+        literals: Vec<Literal>,
+    ) -> Result<Option<ClauseRef>, ()> {
+        let learned = self.clauses.is_learned(clause);
+        let (lbd, activity, last_used) = (
+            self.clauses.lbd(clause),
+            self.clauses.activity(clause),
+            self.clauses.last_used(clause),
+        );
+        self.remove_clause(clause);
+        let replacement = self.store_derived_clause(literals, learned)?;
+        if let Some(replacement) = replacement {
+            if learned {
+                let len = self.clauses.len(replacement);
+                self.clauses.set_lbd(replacement, lbd.min(len));
+                self.clauses.set_activity(replacement, activity);
+                self.clauses.set_last_used(replacement, last_used);
+                self.learned_clauses.push(replacement);
+            }
+        }
+        Ok(replacement)
+    }
+
+    /// Deletes a clause at decision level 0.
+    fn remove_clause(&mut self, clause: ClauseRef) {
+        for literal in self.clauses.literals(clause).iter().take(2) {
+            if self.reason[literal.var()] == Some(Reason::Clause(clause)) {
+                // reasons on level 0 are never looked at
+                self.reason[literal.var()] = None;
+            }
+        }
+        self.detach_clause(clause);
+        self.clauses.delete(clause);
+    }
+
+    /// Forgets deleted learned clauses and compacts the clause database once
+    /// enough clauses have been deleted.
+    fn purge_deleted_clauses(&mut self) {
+        let clauses = &self.clauses;
+        self.learned_clauses
+            .retain(|clause| !clauses.is_deleted(*clause));
+        if self.clauses.needs_collection() {
+            self.collect_garbage();
+        }
+    }
+
+    fn detach_clause(&mut self, clause: ClauseRef) {
+        let (wl1, wl2) = self.clauses.watched_literals(clause);
+        let watches = if self.clauses.len(clause) == 2 {
+            &mut self.binary_watches
+        } else {
+            &mut self.watches
+        };
+        for literal in [wl1, wl2].iter() {
+            watches[literal.index()].retain(|watch| watch.clause != clause);
         }
-        self.restart.2 = self.stats.conflicts + self.restart.0;
-        self.stats.restarts += 1;
-        self.backtrack(0);
-        // let split = (self.learned_clauses.len() / 2) as usize;
-        // for clause in self.learned_clauses.split_off(split) {
-        //     let (l1, l2) = clause.borrow().watched_literals();
-        //     vec_remove(self.watches.get_mut(l1.index()).expect("No watchlist for clause removal"), &clause);
-        //     vec_remove(self.watches.get_mut(l2.index()).expect("No watchlist for clause removal"), &clause);
-        // }
     }
 
     //
     // Utilities
     //
 
-    fn store_assignment(&mut self, literal: Literal, clause: Option<ClauseRef>) -> Result<(), ()> {
+    /// The number of distinct decision levels among the given literals.
+    fn lbd(&mut self, literals: &[Literal]) -> usize {
+        Solver::count_levels(
+            &mut self.level_stamps,
+            &mut self.lbd_stamp,
+            &self.level,
+            self.trail_lim.len(),
+            literals,
+        )
+    }
+
+    /// `lbd` on borrowed fields, so that the literals can live in the clause
+    /// database.
+    fn count_levels(
+        level_stamps: &mut Vec<usize>,
+        lbd_stamp: &mut usize,
+        level: &[usize],
+        decision_level: usize,
+        literals: &[Literal],
+    ) -> usize {
+        *lbd_stamp += 1;
+        if level_stamps.len() <= decision_level {
+            level_stamps.resize(decision_level + 1, 0);
+        }
+        let mut lbd = 0;
+        for literal in literals {
+            let level = level[literal.var()];
+            if level_stamps[level] != *lbd_stamp {
+                level_stamps[level] = *lbd_stamp;
+                lbd += 1;
+            }
+        }
+        lbd
+    }
+
+    /// The highest decision level of the other literals of the reason for
+    /// `literal`, which is the level it is implied on.
+    fn implication_level(&self, literal: Literal, reason: Reason) -> usize {
+        match reason {
+            Reason::Clause(clause) => self
+                .clauses
+                .literals(clause)
+                .iter()
+                .filter(|other| other.var() != literal.var())
+                .map(|other| self.level[other.var()])
+                .max()
+                .unwrap_or(0),
+            Reason::Binary(other) => self.level[other.var()],
+        }
+    }
+
+    fn store_assignment(&mut self, literal: Literal, reason: Option<Reason>) -> Result<(), ()> {
         self.stats.sto_time.start();
-        if let Some(current) = self.assigns.get(literal.var()) {
-            if *current != literal.sign() {
+        if let Some(current) = self.assigns[literal.var()] {
+            if current != literal.sign() {
                 self.stats.sto_time.end();
                 return Err(());
             } else {
@@ -468,16 +1549,24 @@ impl Solver {
                 return Ok(());
             }
         }
-        if let Some(clause) = clause {
-            self.reason.insert(literal.var(), clause);
-        } else {
-            self.trail_lim.push(self.trail.len());
-        }
+        let level = match reason {
+            Some(reason) => {
+                self.reason[literal.var()] = Some(reason);
+                if self.out_of_order {
+                    self.implication_level(literal, reason)
+                } else {
+                    self.trail_lim.len()
+                }
+            }
+            None => {
+                self.trail_lim.push(self.trail.len());
+                self.trail_lim.len()
+            }
+        };
         self.trail.push(literal);
-        self.assigns.insert(literal.var(), literal.sign());
-        self.level.insert(literal.var(), self.trail_lim.len());
-        self.decision_provider.set(literal.var());
-        self.bcp_queue.push_back(literal);
+        self.assigns[literal.var()] = Some(literal.sign());
+        self.level[literal.var()] = level;
+        self.decision_provider.assign(literal);
 
         self.stats.sto_time.end();
 
@@ -485,54 +1574,304 @@ impl Solver {
     }
 }
 
-fn vec_remove<T: PartialEq>(vec: &mut Vec<T>, item: &T) {
-    if let Some(pos) = vec.iter().position(|x| *x == *item) {
-        vec.remove(pos);
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    pub fn new_solver() -> Solver {
+        let mut solver = Solver::new(Box::new(DefaultDecisionProvider::new()));
+        solver.ensure_vars(300);
+        solver
+    }
+
+    fn add(solver: &mut Solver, literals: &[i64]) -> ClauseRef {
+        let literals: Vec<Literal> = literals.iter().map(Literal::new).collect();
+        solver.clauses.add(&literals, false)
+    }
+
     #[test]
     fn backtrack() -> Result<(), ()> {
-        let mut solver = Solver::new();
-
-        let clause = Rc::new(RefCell::new(Clause::new(vec![1, 2, 3])));
-        solver.store_decision(Literal(1))?;
-        solver.store_consequence(Literal(-2), clause.clone())?;
-        solver.store_consequence(Literal(3), clause.clone())?;
-        solver.store_decision(Literal(10))?;
-        solver.store_consequence(Literal(-20), clause.clone())?;
-        solver.store_consequence(Literal(30), clause.clone())?;
-        solver.store_decision(Literal(100))?;
-        solver.store_consequence(Literal(-200), clause.clone())?;
-        solver.store_consequence(Literal(300), clause.clone())?;
+        let mut solver = new_solver();
+
+        let clause = add(&mut solver, &[1, 2, 3]);
+        solver.store_decision(Literal::from(1))?;
+        solver.store_consequence(Literal::from(-2), Reason::Clause(clause))?;
+        solver.store_consequence(Literal::from(3), Reason::Clause(clause))?;
+        solver.store_decision(Literal::from(10))?;
+        solver.store_consequence(Literal::from(-20), Reason::Clause(clause))?;
+        solver.store_consequence(Literal::from(30), Reason::Clause(clause))?;
+        solver.store_decision(Literal::from(100))?;
+        solver.store_consequence(Literal::from(-200), Reason::Clause(clause))?;
+        solver.store_consequence(Literal::from(300), Reason::Clause(clause))?;
 
         assert_eq!(
             vec![
-                Literal(1),
-                Literal(-2),
-                Literal(3),
-                Literal(10),
-                Literal(-20),
-                Literal(30),
-                Literal(100),
-                Literal(-200),
-                Literal(300),
+                Literal::from(1),
+                Literal::from(-2),
+                Literal::from(3),
+                Literal::from(10),
+                Literal::from(-20),
+                Literal::from(30),
+                Literal::from(100),
+                Literal::from(-200),
+                Literal::from(300),
             ],
             solver.trail
         );
         assert_eq!(vec![0, 3, 6], solver.trail_lim);
-        assert_eq!(Some(&1), solver.level.get(1));
-        assert_eq!(Some(&2), solver.level.get(10));
-        assert_eq!(Some(&3), solver.level.get(100));
+        assert_eq!(1, solver.level[1]);
+        assert_eq!(2, solver.level[10]);
+        assert_eq!(3, solver.level[100]);
 
         solver.backtrack(1);
 
-        assert_eq!(vec![Literal(1), Literal(-2), Literal(3)], solver.trail);
+        assert_eq!(
+            vec![Literal::from(1), Literal::from(-2), Literal::from(3)],
+            solver.trail
+        );
         assert_eq!(vec![0], solver.trail_lim);
         Ok(())
     }
+
+    #[test]
+    fn restart_keeps_decisions_that_would_be_made_again() -> Result<(), ()> {
+        let mut solver = new_solver();
+        solver.set_trail_reuse(true);
+        solver.add_clause(vec![1, 2, 3]);
+        solver.add_clause(vec![1, -2, 4]);
+        solver.add_clause(vec![-1, 5]);
+
+        solver.store_decision(Literal::from(1))?;
+        solver.store_decision(Literal::from(3))?;
+        solver.restart(Literal::from(2));
+
+        assert_eq!(vec![Literal::from(1)], solver.trail);
+        assert_eq!(1, solver.stats.reused_trails);
+
+        solver.set_trail_reuse(false);
+        solver.restart(Literal::from(2));
+
+        assert!(solver.trail.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn restart_stops_at_the_first_decision_below_the_next() -> Result<(), ()> {
+        let mut solver = new_solver();
+        solver.set_trail_reuse(true);
+        solver.add_clause(vec![1, 2, 3]);
+        solver.add_clause(vec![1, -2, 4]);
+        solver.add_clause(vec![4, 6]);
+
+        solver.store_decision(Literal::from(1))?;
+        solver.store_decision(Literal::from(3))?;
+        solver.store_decision(Literal::from(4))?;
+        // 4 would be decided before 2, but 3 would not
+        solver.restart(Literal::from(2));
+
+        assert_eq!(vec![Literal::from(1)], solver.trail);
+        assert_eq!(1, solver.trail_lim.len());
+        Ok(())
+    }
+
+    #[test]
+    fn restart_switches_modes() -> Result<(), ()> {
+        let mut solver = new_solver();
+        solver.add_clause(vec![1, 2, 3]);
+
+        solver.stats.conflicts = Solver::MODE_CONFLICTS;
+        solver.store_decision(Literal::from(1))?;
+        assert!(solver.should_restart());
+        solver.restart(Literal::from(2));
+
+        assert!(solver.stable);
+        assert_eq!(1, solver.stats.mode_switches);
+        assert_eq!(2 * Solver::MODE_CONFLICTS, solver.next_mode_switch);
+        assert!(format!("{:?}", solver.restart_policy).starts_with("Luby"));
+
+        solver.stats.conflicts = 2 * Solver::MODE_CONFLICTS;
+        solver.store_decision(Literal::from(1))?;
+        solver.restart(Literal::from(2));
+
+        assert!(!solver.stable);
+        assert_eq!(4 * Solver::MODE_CONFLICTS, solver.next_mode_switch);
+        assert!(format!("{:?}", solver.restart_policy).starts_with("InnerOuter"));
+        Ok(())
+    }
+
+    #[test]
+    fn backtrack_keeps_literals_of_lower_levels_when_out_of_order() -> Result<(), ()> {
+        let mut solver = new_solver();
+        solver.out_of_order = true;
+
+        let clause = add(&mut solver, &[-1, 2]);
+        solver.store_decision(Literal::from(1))?;
+        solver.store_decision(Literal::from(10))?;
+        solver.store_consequence(Literal::from(2), Reason::Clause(clause))?;
+        assert_eq!(1, solver.level[2]);
+
+        solver.backtrack(1);
+
+        assert_eq!(vec![Literal::from(1), Literal::from(2)], solver.trail);
+        assert_eq!(None, solver.assigns[10]);
+        assert_eq!(Some(true), solver.assigns[2]);
+        assert!(solver.qhead <= 1);
+
+        solver.backtrack(0);
+
+        assert!(solver.trail.is_empty());
+        assert!(!solver.out_of_order);
+        Ok(())
+    }
+
+    #[test]
+    fn reduce_learned_clauses_keeps_core_tier2_and_locked_clauses() -> Result<(), ()> {
+        let mut solver = new_solver();
+        let clause = |literals: &[i64]| literals.iter().map(Literal::new).collect();
+
+        solver.add_learned_clause(clause(&[1, 2, 3]), 8, Literal::from(1))?;
+        solver.store_decision(Literal::from(10))?;
+        solver.add_learned_clause(clause(&[4, 5, 6]), 8, Literal::from(4))?;
+        solver.add_learned_clause(clause(&[-4, 5, 6]), 7, Literal::from(5))?;
+        solver.add_learned_clause(clause(&[-4, -5, 6]), 7, Literal::from(6))?;
+        solver.add_learned_clause(clause(&[7, 8, 9]), 2, Literal::from(7))?;
+        solver.add_learned_clause(clause(&[-7, 8, 9]), 4, Literal::from(8))?;
+        for (idx, clause) in solver.learned_clauses.iter().enumerate() {
+            solver.clauses.set_activity(*clause, idx as f64);
+        }
+        solver.backtrack(0);
+        solver.reduce_learned_clauses();
+
+        assert_eq!(5, solver.learned_clauses.len());
+        assert_eq!(1, solver.stats.deleted_clauses);
+        assert_eq!(
+            (1, 1, 3),
+            (
+                solver.stats.core_clauses,
+                solver.stats.tier2_clauses,
+                solver.stats.local_clauses
+            )
+        );
+        assert!(solver
+            .learned_clauses
+            .iter()
+            .all(|clause| solver.clauses.literals(*clause)[0] != Literal::from(4)));
+        assert!(!solver
+            .watches
+            .get(Literal::from(4).index())
+            .unwrap()
+            .iter()
+            .any(|watch| solver.clauses.literals(watch.clause)[0] == Literal::from(4)));
+
+        solver.stats.conflicts = Solver::TIER2_UNUSED;
+        solver.count_tiers();
+        assert_eq!(
+            (1, 0, 4),
+            (
+                solver.stats.core_clauses,
+                solver.stats.tier2_clauses,
+                solver.stats.local_clauses
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn hints_reject_variable_zero() {
+        new_solver().set_priority(0, 1);
+    }
+
+    #[test]
+    fn update_lbd_promotes_clauses() -> Result<(), ()> {
+        let mut solver = new_solver();
+        let literals = [Literal::from(1), Literal::from(2), Literal::from(3)];
+        let clause = solver.clauses.add(&literals, true);
+        solver.clauses.set_lbd(clause, 3);
+        solver.store_decision(Literal::from(-1))?;
+        solver.store_consequence(Literal::from(-2), Reason::Clause(clause))?;
+        solver.store_consequence(Literal::from(3), Reason::Clause(clause))?;
+        solver.update_lbd(clause);
+
+        assert_eq!(1, solver.clauses.lbd(clause));
+        assert_eq!(1, solver.stats.promotions);
+        Ok(())
+    }
+
+    #[test]
+    fn minimize_removes_implied_literals() -> Result<(), ()> {
+        let mut solver = new_solver();
+        let clause = add(&mut solver, &[-1, 2]);
+        solver.store_decision(Literal::from(1))?;
+        solver.store_consequence(Literal::from(2), Reason::Clause(clause))?;
+        solver.store_decision(Literal::from(3))?;
+
+        let mut literals = vec![Literal::from(-1), Literal::from(-2), Literal::from(-3)];
+        solver.minimize(&mut literals, Literal::from(-3));
+        literals.sort_unstable();
+
+        assert_eq!(vec![Literal::from(-1), Literal::from(-3)], literals);
+        assert!(!solver.seen.contains(&true));
+        Ok(())
+    }
+
+    #[test]
+    fn minimize_removes_literals_implied_by_binary_clauses() -> Result<(), ()> {
+        let mut solver = new_solver();
+        solver.add_clause(vec![-3, 5]);
+        solver.store_decision(Literal::from(5))?;
+        solver.store_decision(Literal::from(3))?;
+
+        let mut literals = vec![Literal::from(-5), Literal::from(-3)];
+        solver.set_minimization(Minimization::Recursive);
+        solver.minimize(&mut literals, Literal::from(-3));
+        assert_eq!(vec![Literal::from(-5), Literal::from(-3)], literals);
+
+        solver.set_minimization(Minimization::RecursiveBinary);
+        solver.minimize(&mut literals, Literal::from(-3));
+        assert_eq!(vec![Literal::from(-3)], literals);
+        assert!(!solver.seen.contains(&true));
+        Ok(())
+    }
+
+    #[test]
+    fn analyse_conflict_learns_first_uip_clause() -> Result<(), ()> {
+        let mut solver = new_solver();
+        let reason_3 = add(&mut solver, &[-2, 3]);
+        let reason_4 = add(&mut solver, &[-1, -3, 4]);
+        let conflict = add(&mut solver, &[-3, -4]);
+        solver.store_decision(Literal::from(1))?;
+        solver.store_decision(Literal::from(2))?;
+        solver.store_consequence(Literal::from(3), Reason::Clause(reason_3))?;
+        solver.store_consequence(Literal::from(4), Reason::Clause(reason_4))?;
+
+        let (literals, unit, level) = solver
+            .analyse_conflict(Conflict(conflict))
+            .expect("Conflict above level 0");
+        assert_eq!(vec![Literal::from(-3), Literal::from(-1)], literals);
+        assert_eq!(Literal::from(-3), unit);
+        assert_eq!(1, level);
+        assert!(!solver.seen.contains(&true));
+        Ok(())
+    }
+
+    #[test]
+    fn binary_clauses_propagate_with_binary_reasons() -> Result<(), ()> {
+        let mut solver = new_solver();
+        solver.add_clause(vec![-1, 2]);
+        solver.add_clause(vec![-2, 3]);
+        solver.add_clause(vec![-2, -3]);
+        assert!(solver.watches.iter().all(|watches| watches.is_empty()));
+        solver.store_decision(Literal::from(1))?;
+
+        let conflict = solver.unit_propagate().expect("Binary clauses conflict");
+        assert_eq!(Some(Reason::Binary(Literal::from(-1))), solver.reason[2]);
+        let (literals, unit, level) = solver
+            .analyse_conflict(conflict)
+            .expect("Conflict above level 0");
+        assert_eq!(vec![Literal::from(-2)], literals);
+        assert_eq!(Literal::from(-2), unit);
+        assert_eq!(0, level);
+        Ok(())
+    }
 }
@@ -0,0 +1,233 @@
+use clause::ClauseRef;
+use literal::Literal;
+
+use super::{ClauseElimination, Solver};
+
+/// What resolution on the literals of a clause shows.
+#[derive(Debug, Eq, PartialEq)]
+enum Blocking {
+    /// All resolvents on the literal are tautologies.
+    Blocked(Literal),
+    /// All resolvents on the literal that are not tautologies contain the
+    /// given literals missing from the clause.
+    Covers(Literal, Vec<Literal>),
+    Neither,
+}
+
+impl Solver {
+    // literals with more occurrences of their negation do not block clauses
+    const BLOCKING_OCCURRENCES: usize = 100;
+    // clauses longer than this, also after adding covered literals, are kept
+    const BLOCKED_CLAUSE_SIZE: usize = 100;
+
+    /// Removes irredundant clauses that are blocked: clauses with a literal
+    /// whose resolvents with all clauses containing its negation are
+    /// tautologies. With `ClauseElimination::Covered`, literals contained in
+    /// all such resolvents that are not tautologies are added to the clause
+    /// first, which makes more clauses blocked or tautologies.
+    ///
+    /// Removed clauses are satisfied in the model by making their blocking
+    /// literal true. Variables with hints never block clauses.
+    ///
+    /// https://doi.org/10.1613/jair.4694
+    pub(super) fn eliminate_blocked_clauses(&mut self) {
+        let occurrences = self.occurrence_lists(false);
+        let candidates: Vec<ClauseRef> = self
+            .clauses
+            .iter()
+            .filter(|clause| {
+                !self.clauses.is_learned(*clause)
+                    && self.clauses.len(*clause) <= Solver::BLOCKED_CLAUSE_SIZE
+                    && !self.is_satisfied(*clause)
+            })
+            .collect();
+        let mut marks = vec![false; self.watches.len()];
+        for clause in candidates {
+            if self.clauses.is_deleted(clause) {
+                continue;
+            }
+            let mut literals = self.clauses.literals(clause).to_vec();
+            for literal in literals.iter() {
+                marks[literal.index()] = true;
+            }
+            // the covered clauses with the literal they were extended on
+            let mut covered = Vec::new();
+            let blocked = loop {
+                match self.blocking(clause, &literals, &marks, &occurrences) {
+                    Blocking::Blocked(literal) => break Some(literal),
+                    Blocking::Covers(literal, extension) => {
+                        covered.push((literal, literals.clone()));
+                        if extension.iter().any(|extended| marks[(!*extended).index()]) {
+                            // the covered clause is a tautology
+                            break None;
+                        }
+                        for extended in extension {
+                            marks[extended.index()] = true;
+                            literals.push(extended);
+                        }
+                        if literals.len() > Solver::BLOCKED_CLAUSE_SIZE {
+                            covered.clear();
+                            break None;
+                        }
+                    }
+                    Blocking::Neither => {
+                        covered.clear();
+                        break None;
+                    }
+                }
+            };
+            if blocked.is_some() || !covered.is_empty() {
+                for (literal, literals) in covered {
+                    self.reconstruction.push(literal, &literals);
+                }
+                if let Some(literal) = blocked {
+                    self.reconstruction.push(literal, &literals);
+                }
+                self.remove_clause(clause);
+                self.stats.blocked_clauses += 1;
+            }
+            for literal in literals {
+                marks[literal.index()] = false;
+            }
+        }
+        self.purge_deleted_clauses();
+    }
+
+    /// Whether one of the marked `literals` of `clause` blocks it, or,
+    /// when covered clauses are eliminated, adds covered literals to it.
+    fn blocking(
+        &self,
+        clause: ClauseRef,
+        literals: &[Literal],
+        marks: &[bool],
+        occurrences: &[Vec<ClauseRef>],
+    ) -> Blocking {
+        let covered = self.clause_elimination == ClauseElimination::Covered;
+        let mut extension = Blocking::Neither;
+        for literal in literals.iter() {
+            let others = &occurrences[(!*literal).index()];
+            // clauses satisfied on decision level 0 are not in `occurrences`
+            if self.frozen[literal.var()]
+                || self.assigns[literal.var()].is_some()
+                || others.len() > Solver::BLOCKING_OCCURRENCES
+            {
+                continue;
+            }
+            // literals in all resolvents that are not tautologies so far
+            let mut common: Option<Vec<Literal>> = None;
+            for other in others.iter() {
+                if *other == clause || self.clauses.is_deleted(*other) {
+                    continue;
+                }
+                let resolvent = self.clauses.literals(*other);
+                if resolvent
+                    .iter()
+                    .any(|resolved| *resolved != !*literal && marks[(!*resolved).index()])
+                {
+                    continue;
+                }
+                let missing = resolvent
+                    .iter()
+                    .cloned()
+                    .filter(|resolved| *resolved != !*literal && !marks[resolved.index()]);
+                common = Some(match common {
+                    None => missing.collect(),
+                    Some(common) => missing
+                        .filter(|resolved| common.contains(resolved))
+                        .collect(),
+                });
+                if !covered || common.as_ref().is_some_and(Vec::is_empty) {
+                    break;
+                }
+            }
+            match common {
+                None => return Blocking::Blocked(*literal),
+                Some(common) => {
+                    if covered && extension == Blocking::Neither && !common.is_empty() {
+                        extension = Blocking::Covers(*literal, common);
+                    }
+                }
+            }
+        }
+        extension
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::new_solver;
+    use super::*;
+
+    fn remaining(solver: &Solver) -> Vec<Vec<i64>> {
+        solver
+            .clauses
+            .iter()
+            .map(|clause| {
+                let mut literals: Vec<i64> = solver
+                    .clauses
+                    .literals(clause)
+                    .iter()
+                    .map(Literal::to_dimacs)
+                    .collect();
+                literals.sort();
+                literals
+            })
+            .collect()
+    }
+
+    #[test]
+    fn eliminate_blocked_clauses_removes_blocked_clauses() {
+        let mut solver = new_solver();
+        solver.set_clause_elimination(ClauseElimination::Blocked);
+        solver.add_clause(vec![1, 2]);
+        solver.add_clause(vec![-1, -2, 3]);
+        solver.add_clause(vec![-3, 4]);
+        // 5 6 7 is not blocked on 5, which is false on decision level 0
+        solver.add_clause(vec![-5]);
+        solver.add_clause(vec![5, 6, 7]);
+        for var in [3, 4, 6, 7].iter() {
+            solver.freeze(*var);
+        }
+        assert!(solver.unit_propagate().is_none());
+
+        solver.eliminate_blocked_clauses();
+        assert_eq!(2, solver.stats.blocked_clauses);
+        assert_eq!(
+            vec![vec![-3, 4], vec![-5], vec![5, 6, 7]],
+            remaining(&solver)
+        );
+
+        let mut model = vec![None, Some(false), Some(false), Some(false), Some(false)];
+        solver.reconstruction.extend(&mut model);
+        assert_eq!(vec![Some(true), Some(false)], model[1..3].to_vec());
+    }
+
+    #[test]
+    fn eliminate_blocked_clauses_removes_covered_clauses() {
+        let mut solver = new_solver();
+        solver.add_clause(vec![1, 2]);
+        solver.add_clause(vec![-1, 3]);
+        solver.add_clause(vec![-1, 3, 4]);
+        solver.add_clause(vec![-2, -3]);
+        solver.add_clause(vec![-4, 5]);
+        for var in 3..6 {
+            solver.freeze(var);
+        }
+        solver.set_clause_elimination(ClauseElimination::Blocked);
+        solver.eliminate_blocked_clauses();
+        assert_eq!(0, solver.stats.blocked_clauses);
+
+        // 1 2 3 is blocked on 2 after adding 3, which is covered by 1
+        solver.set_clause_elimination(ClauseElimination::Covered);
+        solver.eliminate_blocked_clauses();
+        assert_eq!(4, solver.stats.blocked_clauses);
+        assert_eq!(vec![vec![-4, 5]], remaining(&solver));
+
+        let mut model = vec![None, Some(false), Some(false), Some(false), None, None];
+        solver.reconstruction.extend(&mut model);
+        assert_eq!(
+            vec![Some(false), Some(true), Some(false)],
+            model[1..4].to_vec()
+        );
+    }
+}
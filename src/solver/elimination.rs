@@ -0,0 +1,226 @@
+use clause::ClauseRef;
+use literal::Literal;
+
+use super::{Solver, VariableName};
+
+impl Solver {
+    // variables with more occurrences of either literal are not eliminated
+    const ELIMINATION_OCCURRENCES: usize = 20;
+    // clauses and resolvents longer than this are not resolved or added
+    const ELIMINATION_CLAUSE_SIZE: usize = 20;
+
+    /// Removes variables by replacing the clauses they occur in with all
+    /// their non-tautological resolvents, as long as that does not increase
+    /// the number of clauses. Only runs before the search.
+    ///
+    /// http://fmv.jku.at/papers/EenBiere-SAT05.pdf
+    pub(super) fn eliminate_variables(&mut self) -> Result<(), ()> {
+        let mut occurrences = self.occurrence_lists(false);
+        let mut candidates: Vec<VariableName> = (1..self.assigns.len())
+            .filter(|var| !self.frozen[*var] && self.assigns[*var].is_none())
+            .collect();
+        candidates.sort_by_key(|var| {
+            let positive = Literal::from_var(*var, true);
+            occurrences[positive.index()].len() * occurrences[(!positive).index()].len()
+        });
+        for var in candidates {
+            if self.assigns[var].is_none() {
+                self.eliminate_variable(var, &mut occurrences)?;
+            }
+        }
+        // eliminated variables get their values from the reconstruction only
+        let learned: Vec<ClauseRef> = self
+            .learned_clauses
+            .iter()
+            .cloned()
+            .filter(|clause| {
+                self.clauses
+                    .literals(*clause)
+                    .iter()
+                    .any(|literal| self.eliminated[literal.var()])
+            })
+            .collect();
+        for clause in learned {
+            self.remove_clause(clause);
+        }
+        self.purge_deleted_clauses();
+        Ok(())
+    }
+
+    /// For each literal, the irredundant clauses, and the learned ones if
+    /// `learned` is set, that contain it and are not satisfied on decision
+    /// level 0.
+    pub(super) fn occurrence_lists(&self, learned: bool) -> Vec<Vec<ClauseRef>> {
+        let mut occurrences = vec![Vec::new(); self.watches.len()];
+        for clause in self.clauses.iter() {
+            if (!learned && self.clauses.is_learned(clause)) || self.is_satisfied(clause) {
+                continue;
+            }
+            for literal in self.clauses.literals(clause) {
+                occurrences[literal.index()].push(clause);
+            }
+        }
+        occurrences
+    }
+
+    pub(super) fn is_satisfied(&self, clause: ClauseRef) -> bool {
+        self.clauses
+            .literals(clause)
+            .iter()
+            .any(|literal| literal.satisfied_by(self.assigns[literal.var()]))
+    }
+
+    fn eliminate_variable(
+        &mut self,
+        var: VariableName,
+        occurrences: &mut [Vec<ClauseRef>],
+    ) -> Result<(), ()> {
+        let positive = Literal::from_var(var, true);
+        let (pos, neg) = (
+            self.live_occurrences(positive, occurrences),
+            self.live_occurrences(!positive, occurrences),
+        );
+        // tautologies occur in both lists
+        if (pos.is_empty() && neg.is_empty())
+            || pos.iter().any(|clause| neg.contains(clause))
+            || pos.len() > Solver::ELIMINATION_OCCURRENCES
+            || neg.len() > Solver::ELIMINATION_OCCURRENCES
+            || pos
+                .iter()
+                .chain(neg.iter())
+                .any(|clause| self.clauses.len(*clause) > Solver::ELIMINATION_CLAUSE_SIZE)
+        {
+            return Ok(());
+        }
+
+        let mut resolvents = Vec::new();
+        for p in pos.iter() {
+            for n in neg.iter() {
+                if let Some(resolvent) = self.resolve(*p, *n, var) {
+                    if resolvent.len() > Solver::ELIMINATION_CLAUSE_SIZE
+                        || resolvents.len() == pos.len() + neg.len()
+                    {
+                        return Ok(());
+                    }
+                    resolvents.push(resolvent);
+                }
+            }
+        }
+
+        debug!("Eliminate {} with {} resolvents", var, resolvents.len());
+        let (kept, witness) = if pos.len() <= neg.len() {
+            (&pos, positive)
+        } else {
+            (&neg, !positive)
+        };
+        for clause in kept.iter() {
+            self.reconstruction
+                .push(witness, self.clauses.literals(*clause));
+        }
+        self.reconstruction.push(!witness, &[!witness]);
+        for clause in pos.iter().chain(neg.iter()) {
+            self.remove_clause(*clause);
+        }
+        occurrences[positive.index()].clear();
+        occurrences[(!positive).index()].clear();
+        self.eliminated[var] = true;
+        self.decision_provider.set_decision(var, false);
+        self.stats.eliminated_variables += 1;
+
+        for resolvent in resolvents {
+            if let Some(clause) = self.add_derived_clause(resolvent, false)? {
+                for literal in self.clauses.literals(clause) {
+                    occurrences[literal.index()].push(clause);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The clauses in the occurrence list of `literal` that are neither
+    /// deleted nor satisfied.
+    pub(super) fn live_occurrences(
+        &self,
+        literal: Literal,
+        occurrences: &[Vec<ClauseRef>],
+    ) -> Vec<ClauseRef> {
+        occurrences[literal.index()]
+            .iter()
+            .cloned()
+            .filter(|clause| !self.clauses.is_deleted(*clause) && !self.is_satisfied(*clause))
+            .collect()
+    }
+
+    /// The resolvent of two clauses on `var` without the literals that are
+    /// false on decision level 0, or `None` if it is a tautology or
+    /// satisfied.
+    fn resolve(
+        &self,
+        first: ClauseRef,
+        second: ClauseRef,
+        var: VariableName,
+    ) -> Option<Vec<Literal>> {
+        let mut resolvent: Vec<Literal> = Vec::new();
+        let literals = self.clauses.literals(first);
+        for literal in literals.iter().chain(self.clauses.literals(second)) {
+            if literal.var() == var || literal.falsified_by(self.assigns[literal.var()]) {
+                continue;
+            }
+            if literal.satisfied_by(self.assigns[literal.var()]) || resolvent.contains(&!*literal) {
+                return None;
+            }
+            if !resolvent.contains(literal) {
+                resolvent.push(*literal);
+            }
+        }
+        Some(resolvent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::new_solver;
+    use super::*;
+
+    fn sorted(clauses: Vec<Vec<Literal>>) -> Vec<Vec<Literal>> {
+        let mut clauses: Vec<Vec<Literal>> = clauses
+            .into_iter()
+            .map(|mut clause| {
+                clause.sort();
+                clause
+            })
+            .collect();
+        clauses.sort();
+        clauses
+    }
+
+    #[test]
+    fn eliminate_variables_replaces_clauses_by_resolvents() {
+        let mut solver = new_solver();
+        solver.add_clause(vec![1, 2, 3]);
+        solver.add_clause(vec![-3, 4]);
+        solver.add_clause(vec![-3, 5]);
+        solver.add_clause(vec![-4, -5, 6]);
+        for var in [1, 2, 4, 5, 6].iter() {
+            solver.freeze(*var);
+        }
+        assert_eq!(Ok(()), solver.eliminate_variables());
+
+        assert!(solver.eliminated[3]);
+        let clauses = solver
+            .clauses
+            .iter()
+            .map(|clause| solver.clauses.literals(clause).to_vec())
+            .collect();
+        let expected = vec![vec![1, 2, 4], vec![1, 2, 5], vec![-4, -5, 6]]
+            .into_iter()
+            .map(|clause| clause.into_iter().map(Literal::from).collect())
+            .collect();
+        assert_eq!(sorted(expected), sorted(clauses));
+
+        let mut model = vec![None, Some(false), Some(false), None];
+        model.extend(vec![Some(true); 3]);
+        solver.reconstruction.extend(&mut model);
+        assert_eq!(Some(true), model[3]);
+    }
+}
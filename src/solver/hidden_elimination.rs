@@ -0,0 +1,169 @@
+use clause::ClauseRef;
+use literal::Literal;
+
+use super::{Solver, Watch};
+
+/// Discovery and finishing times of the literals in a depth-first search of
+/// the binary implication graph.
+struct Stamps {
+    discovered: Vec<usize>,
+    finished: Vec<usize>,
+}
+
+impl Stamps {
+    /// Whether `from` implies `to` through binary clauses, which is the case
+    /// if `to` was visited while searching from `from`. Implications through
+    /// other paths are missed.
+    fn implies(&self, from: Literal, to: Literal) -> bool {
+        self.discovered[from.index()] < self.discovered[to.index()]
+            && self.finished[to.index()] < self.finished[from.index()]
+    }
+}
+
+impl Solver {
+    // longer clauses are not checked
+    const HIDDEN_CLAUSE_SIZE: usize = 100;
+
+    /// Removes clauses with literals `a` and `b` where `¬a` implies `b`
+    /// through binary clauses (hidden tautology elimination), and literals
+    /// `a` of clauses with another literal `b` that `a` implies (hidden
+    /// literal elimination). Both keep the formula equivalent, so nothing
+    /// needs to be reconstructed.
+    ///
+    /// Implications are read off time stamps of a depth-first search of the
+    /// implication graph of the irredundant binary clauses, so that each
+    /// check takes constant time. Binary clauses themselves are not checked.
+    /// Stops after about `steps` edges and literal pairs and returns how
+    /// many it looked at.
+    ///
+    /// https://doi.org/10.1007/978-3-642-21581-0_17
+    pub(super) fn eliminate_hidden(&mut self, steps: usize) -> Result<usize, ()> {
+        let (stamps, mut spent) = self.stamps();
+        let candidates: Vec<ClauseRef> = self
+            .clauses
+            .iter()
+            .filter(|clause| {
+                let len = self.clauses.len(*clause);
+                len > 2 && len <= Solver::HIDDEN_CLAUSE_SIZE
+            })
+            .collect();
+        for clause in candidates {
+            if spent >= steps {
+                break;
+            }
+            if self.clauses.is_deleted(clause) || self.is_satisfied(clause) {
+                continue;
+            }
+            let mut literals = self.clauses.literals(clause).to_vec();
+            spent += literals.len() * literals.len();
+            let tautology = literals
+                .iter()
+                .any(|a| literals.iter().any(|b| a != b && stamps.implies(!*a, *b)));
+            if tautology {
+                self.remove_clause(clause);
+                self.stats.hidden_tautologies += 1;
+                continue;
+            }
+            let len = literals.len();
+            let mut next = 0;
+            while next < literals.len() {
+                let a = literals[next];
+                if literals.iter().any(|b| a != *b && stamps.implies(a, *b)) {
+                    literals.remove(next);
+                } else {
+                    next += 1;
+                }
+            }
+            if literals.len() < len {
+                self.stats.hidden_literals += len - literals.len();
+                self.replace_clause(clause, literals)?;
+            }
+        }
+
+        self.purge_deleted_clauses();
+        Ok(spent)
+    }
+
+    /// Time stamps of the unassigned literals in a depth-first search of the
+    /// implication graph of the irredundant binary clauses, started from
+    /// the literals no other literal implies first, and the number of edges
+    /// looked at.
+    fn stamps(&self) -> (Stamps, usize) {
+        let literals = self.watches.len();
+        let mut stamps = Stamps {
+            discovered: vec![0; literals],
+            finished: vec![0; literals],
+        };
+        let is_edge = |watch: &Watch| {
+            !self.clauses.is_learned(watch.clause) && self.assigns[watch.blocker.var()].is_none()
+        };
+        let (roots, others): (Vec<Literal>, Vec<Literal>) = (2..literals)
+            .map(Literal::from_index)
+            .filter(|literal| self.assigns[literal.var()].is_none())
+            .partition(|literal| !self.binary_watches[literal.index()].iter().any(is_edge));
+        let mut stamp = 0;
+        let mut edges = 0;
+        for start in roots.into_iter().chain(others) {
+            if stamps.discovered[start.index()] != 0 {
+                continue;
+            }
+            stamp += 1;
+            stamps.discovered[start.index()] = stamp;
+            // the visited literals with the position of their next successor
+            let mut calls = vec![(start, 0)];
+            while let Some(&(node, edge)) = calls.last() {
+                let successors = &self.binary_watches[(!node).index()];
+                if edge < successors.len() {
+                    calls.last_mut().expect("Empty call stack").1 += 1;
+                    edges += 1;
+                    let successor = successors[edge].blocker;
+                    if is_edge(&successors[edge]) && stamps.discovered[successor.index()] == 0 {
+                        stamp += 1;
+                        stamps.discovered[successor.index()] = stamp;
+                        calls.push((successor, 0));
+                    }
+                    continue;
+                }
+                calls.pop();
+                stamp += 1;
+                stamps.finished[node.index()] = stamp;
+            }
+        }
+        (stamps, edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::new_solver;
+    use super::*;
+
+    #[test]
+    fn eliminate_hidden_removes_hidden_tautologies_and_literals() {
+        let mut solver = new_solver();
+        // -1 implies 2, which implies 3
+        solver.add_clause(vec![1, 2]);
+        solver.add_clause(vec![-2, 3]);
+        solver.add_clause(vec![1, 3, 4]);
+        solver.add_clause(vec![2, 3, 5]);
+
+        assert!(solver.eliminate_hidden(1000).is_ok());
+        assert_eq!(1, solver.stats.hidden_tautologies);
+        assert_eq!(1, solver.stats.hidden_literals);
+        let clauses: Vec<Vec<i64>> = solver
+            .clauses
+            .iter()
+            .map(|clause| {
+                let mut literals: Vec<i64> = solver
+                    .clauses
+                    .literals(clause)
+                    .iter()
+                    .map(Literal::to_dimacs)
+                    .collect();
+                literals.sort();
+                literals
+            })
+            .collect();
+        assert_eq!(vec![vec![1, 2], vec![-2, 3], vec![3, 5]], clauses);
+    }
+}
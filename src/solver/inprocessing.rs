@@ -0,0 +1,200 @@
+use std::cmp;
+
+use super::{Solver, StatTime};
+
+/// The simplifications that are interleaved with the search.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum Technique {
+    Probing,
+    Substitution,
+    Subsumption,
+    Vivification,
+    HiddenElimination,
+}
+
+impl Technique {
+    pub(super) const ALL: [Technique; 5] = [
+        Technique::Probing,
+        Technique::Substitution,
+        Technique::Subsumption,
+        Technique::HiddenElimination,
+        Technique::Vivification,
+    ];
+
+    /// Ticks a run may spend per search propagation since the last run. A
+    /// tick is a propagation, except for subsumption, where it is a literal
+    /// of a compared clause, and hidden elimination, where it is an edge of
+    /// the binary implication graph or a pair of literals of a clause.
+    fn effort(self) -> f64 {
+        match self {
+            Technique::Probing => 0.1,
+            // linear in the binary implication graph, so not limited
+            Technique::Substitution => 0.0,
+            Technique::Subsumption => 1.0,
+            Technique::Vivification => 0.05,
+            Technique::HiddenElimination => 1.0,
+        }
+    }
+}
+
+/// When an inprocessing technique runs next and how much it may spend.
+#[derive(Debug)]
+pub(super) struct Schedule {
+    // number of conflicts after which the technique runs next
+    next: usize,
+    // number of conflicts between two runs, grows while runs simplify nothing
+    interval: usize,
+    // search propagations when the technique ran last
+    last_search: usize,
+}
+
+impl Schedule {
+    pub(super) fn new() -> Schedule {
+        Schedule {
+            next: Solver::INPROCESSING_INTERVAL,
+            interval: Solver::INPROCESSING_INTERVAL,
+            last_search: 0,
+        }
+    }
+}
+
+impl Solver {
+    // ticks a run may spend however little was searched since the last run
+    const MIN_INPROCESSING_TICKS: usize = 10_000;
+
+    /// Runs the enabled inprocessing techniques that are due, on decision
+    /// level 0 after a restart. Each gets a tick budget relative to the
+    /// propagations of the search since its last run. A technique that
+    /// simplifies nothing runs half as often afterwards, one that does
+    /// simplify goes back towards running every `INPROCESSING_INTERVAL`
+    /// conflicts. Fails if the formula is found to be unsatisfiable.
+    pub(super) fn inprocess(&mut self) -> Result<(), ()> {
+        for technique in Technique::ALL.iter().cloned() {
+            if !self.is_enabled(technique)
+                || self.stats.conflicts < self.schedules[technique as usize].next
+            {
+                continue;
+            }
+            let search = self.stats.propagations - self.stats.inprocessing_propagations;
+            let searched = search - self.schedules[technique as usize].last_search;
+            let budget = cmp::max(
+                Solver::MIN_INPROCESSING_TICKS,
+                (searched as f64 * technique.effort()) as usize,
+            );
+            let simplifications = self.simplifications(technique);
+            self.run_technique(technique, budget)?;
+            let simplified = self.simplifications(technique) > simplifications;
+
+            let schedule = &mut self.schedules[technique as usize];
+            schedule.interval = if simplified {
+                cmp::max(schedule.interval / 2, Solver::INPROCESSING_INTERVAL)
+            } else {
+                schedule.interval * 2
+            };
+            schedule.next = self.stats.conflicts + schedule.interval;
+            schedule.last_search = search;
+        }
+        Ok(())
+    }
+
+    /// Whether `inprocess` would run any technique.
+    pub(super) fn inprocessing_due(&self) -> bool {
+        Technique::ALL.iter().any(|technique| {
+            self.is_enabled(*technique)
+                && self.stats.conflicts >= self.schedules[*technique as usize].next
+        })
+    }
+
+    /// Runs `technique` with a budget of `ticks`, keeping track of the time
+    /// and the ticks it spends.
+    pub(super) fn run_technique(&mut self, technique: Technique, ticks: usize) -> Result<(), ()> {
+        let propagations = self.stats.propagations;
+        self.technique_time(technique).start();
+        let spent = match technique {
+            Technique::Probing => self.probe(ticks),
+            Technique::Substitution => self.substitute_equivalences(),
+            Technique::Subsumption => self.subsume_clauses(ticks),
+            Technique::Vivification => self.vivify_clauses(ticks),
+            Technique::HiddenElimination => self.eliminate_hidden(ticks),
+        };
+        self.technique_time(technique).end();
+        self.stats.inprocessing_propagations += self.stats.propagations - propagations;
+        *self.technique_ticks(technique) += spent?;
+        Ok(())
+    }
+
+    fn is_enabled(&self, technique: Technique) -> bool {
+        match technique {
+            Technique::Probing => self.probing,
+            Technique::Substitution => self.substitution,
+            Technique::Subsumption => self.subsumption,
+            Technique::Vivification => self.vivification,
+            Technique::HiddenElimination => self.hidden_elimination,
+        }
+    }
+
+    /// The number of simplifications `technique` made so far.
+    fn simplifications(&self, technique: Technique) -> usize {
+        match technique {
+            Technique::Probing => self.stats.failed_literals + self.stats.hyper_binary_resolvents,
+            Technique::Substitution => self.stats.substituted_variables,
+            Technique::Subsumption => self.stats.subsumed_clauses + self.stats.strengthened_clauses,
+            Technique::Vivification => self.stats.vivified_clauses,
+            Technique::HiddenElimination => {
+                self.stats.hidden_tautologies + self.stats.hidden_literals
+            }
+        }
+    }
+
+    fn technique_time(&mut self, technique: Technique) -> &mut StatTime {
+        match technique {
+            Technique::Probing => &mut self.stats.probing_time,
+            Technique::Substitution => &mut self.stats.substitution_time,
+            Technique::Subsumption => &mut self.stats.subsumption_time,
+            Technique::Vivification => &mut self.stats.vivification_time,
+            Technique::HiddenElimination => &mut self.stats.hidden_elimination_time,
+        }
+    }
+
+    fn technique_ticks(&mut self, technique: Technique) -> &mut usize {
+        match technique {
+            Technique::Probing => &mut self.stats.probing_ticks,
+            Technique::Substitution => &mut self.stats.substitution_ticks,
+            Technique::Subsumption => &mut self.stats.subsumption_ticks,
+            Technique::Vivification => &mut self.stats.vivification_ticks,
+            Technique::HiddenElimination => &mut self.stats.hidden_elimination_ticks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::new_solver;
+    use super::*;
+
+    #[test]
+    fn inprocess_runs_techniques_that_simplify_more_often() {
+        let mut solver = new_solver();
+        solver.add_clause(vec![1, 2]);
+        solver.add_clause(vec![1, 2, 3]);
+        solver.add_clause(vec![-1, 4, 5]);
+        solver.set_probing(false);
+        solver.set_vivification(false);
+        solver.set_hidden_elimination(false);
+
+        assert_eq!(Ok(()), solver.inprocess());
+        assert_eq!(0, solver.stats.subsumed_clauses);
+
+        solver.stats.conflicts = Solver::INPROCESSING_INTERVAL;
+        assert_eq!(Ok(()), solver.inprocess());
+        assert_eq!(1, solver.stats.subsumed_clauses);
+        assert_eq!(1, solver.stats.subsumption_time.2);
+        let subsumption = &solver.schedules[Technique::Subsumption as usize];
+        assert_eq!(2 * Solver::INPROCESSING_INTERVAL, subsumption.next);
+        assert!(solver.stats.subsumption_ticks > 0);
+        // nothing to substitute, so substitution runs half as often
+        let substitution = &solver.schedules[Technique::Substitution as usize];
+        assert_eq!(3 * Solver::INPROCESSING_INTERVAL, substitution.next);
+        assert_eq!(0, solver.stats.probing_time.2);
+    }
+}
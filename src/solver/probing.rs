@@ -0,0 +1,208 @@
+use std::collections::{HashMap, HashSet};
+
+use literal::Literal;
+
+use super::{Reason, Solver, VariableName};
+
+impl Solver {
+    /// Failed literal probing on the roots of the binary implication graph,
+    /// the literals that imply others through binary clauses but are implied
+    /// by none. Each root and its negation are assigned on decision level 1
+    /// and propagated. If that leads to a conflict the negation of the
+    /// probed literal is a unit, and so is every literal implied by both.
+    /// A literal implied through a longer clause gets a binary clause with
+    /// the negation of the dominator of the clause's false literals in the
+    /// binary implication tree of the probed literal, unless it already has
+    /// one (hyper-binary resolution).
+    ///
+    /// Stops after about `propagations` propagations and returns how many
+    /// were made.
+    ///
+    /// https://doi.org/10.1007/978-3-642-21581-0_17
+    pub(super) fn probe(&mut self, propagations: usize) -> Result<usize, ()> {
+        let start = self.stats.propagations;
+        let budget = start + propagations;
+        let roots: Vec<Literal> = (2..self.watches.len())
+            .map(Literal::from_index)
+            .filter(|literal| {
+                self.binary_watches[literal.index()].is_empty()
+                    && !self.binary_watches[(!*literal).index()].is_empty()
+            })
+            .collect();
+        let mut implied = vec![false; self.watches.len()];
+        for root in roots {
+            if self.stats.propagations >= budget {
+                break;
+            }
+            if self.assigns[root.var()].is_some() {
+                continue;
+            }
+            let positive = match self.probe_literal(root)? {
+                Some(literals) => literals,
+                None => continue,
+            };
+            for literal in positive.iter() {
+                implied[literal.index()] = true;
+            }
+            let negative = if self.assigns[root.var()].is_none() {
+                self.probe_literal(!root)?
+            } else {
+                None
+            };
+            if let Some(negative) = negative {
+                for literal in negative {
+                    if implied[literal.index()] && self.assigns[literal.var()].is_none() {
+                        self.stats.failed_literals += 1;
+                        self.add_derived_clause(vec![literal], false)?;
+                    }
+                }
+            }
+            for literal in positive {
+                implied[literal.index()] = false;
+            }
+        }
+        Ok(self.stats.propagations - start)
+    }
+
+    /// Assigns `literal` on decision level 1 and returns the literals it
+    /// implies, or `None` if it leads to a conflict and its negation was
+    /// learned as a unit.
+    fn probe_literal(&mut self, literal: Literal) -> Result<Option<Vec<Literal>>, ()> {
+        self.store_decision(literal)?;
+        if self.unit_propagate().is_some() {
+            self.backtrack(0);
+            self.stats.failed_literals += 1;
+            self.add_derived_clause(vec![!literal], false)?;
+            return Ok(None);
+        }
+        let implied = self.trail[self.trail_lim[0] + 1..].to_vec();
+        // for each implied variable, the literal that implies it in the tree
+        let mut parents: HashMap<VariableName, Literal> = HashMap::new();
+        let mut resolvents = Vec::new();
+        for implied in implied.iter().cloned() {
+            let parent = match self.reason[implied.var()] {
+                Some(Reason::Binary(other)) => !other,
+                Some(Reason::Clause(clause)) => {
+                    let mut falsified = self
+                        .clauses
+                        .literals(clause)
+                        .iter()
+                        .filter(|other| other.var() != implied.var() && self.level[other.var()] > 0)
+                        .map(|other| !*other);
+                    let first = falsified.next().expect("Implied without a reason");
+                    let dominator = falsified.fold(first, |dominator, other| {
+                        Solver::dominator(&parents, dominator, other)
+                    });
+                    if self.clauses.len(clause) > 2 && !self.implies_binary(dominator, implied) {
+                        resolvents.push((dominator, implied));
+                    }
+                    dominator
+                }
+                None => continue,
+            };
+            parents.insert(implied.var(), parent);
+        }
+        self.backtrack(0);
+        for (dominator, implied) in resolvents {
+            if let Some(clause) = self.add_derived_clause(vec![!dominator, implied], true)? {
+                self.clauses.set_lbd(clause, 2);
+                self.clauses.set_last_used(clause, self.stats.conflicts);
+                self.learned_clauses.push(clause);
+                self.stats.hyper_binary_resolvents += 1;
+            }
+        }
+        Ok(Some(implied))
+    }
+
+    /// The closest common ancestor of two literals in the tree given by
+    /// `parents`, whose root is the only literal without a parent.
+    fn dominator(
+        parents: &HashMap<VariableName, Literal>,
+        first: Literal,
+        second: Literal,
+    ) -> Literal {
+        let mut ancestors = HashSet::new();
+        let mut literal = Some(first);
+        while let Some(ancestor) = literal {
+            ancestors.insert(ancestor);
+            literal = parents.get(&ancestor.var()).cloned();
+        }
+        let mut literal = second;
+        while !ancestors.contains(&literal) {
+            literal = parents[&literal.var()];
+        }
+        literal
+    }
+
+    /// Whether `from` implies `to` through a binary clause.
+    fn implies_binary(&self, from: Literal, to: Literal) -> bool {
+        self.binary_watches[(!from).index()]
+            .iter()
+            .any(|watch| watch.blocker == to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::new_solver;
+    use super::*;
+
+    #[test]
+    fn probe_learns_failed_literals_and_hyper_binary_resolvents() {
+        let mut solver = new_solver();
+        // 1 fails
+        solver.add_clause(vec![-1, 2]);
+        solver.add_clause(vec![-1, 3]);
+        solver.add_clause(vec![-2, -3]);
+        // 4 implies 6 through a longer clause
+        solver.add_clause(vec![-4, 5]);
+        solver.add_clause(vec![-5, 7]);
+        solver.add_clause(vec![-4, -5, 6, -7]);
+
+        assert!(solver.probe(1000).is_ok());
+        assert_eq!(Some(false), solver.assigns[1]);
+        assert!(solver.assigns[4..8].iter().all(Option::is_none));
+        assert_eq!(1, solver.stats.failed_literals);
+        let resolvents: Vec<&[Literal]> = solver
+            .learned_clauses
+            .iter()
+            .map(|clause| solver.clauses.literals(*clause))
+            .collect();
+        assert_eq!(vec![&[Literal::from(-4), Literal::from(6)][..]], resolvents);
+        assert!(solver.trail_lim.is_empty());
+    }
+
+    #[test]
+    fn probe_resolves_with_the_dominator_once() {
+        let mut solver = new_solver();
+        solver.add_clause(vec![-1, 2]);
+        solver.add_clause(vec![-2, 3]);
+        solver.add_clause(vec![-2, 4]);
+        solver.add_clause(vec![-3, -4, 5]);
+
+        assert!(solver.probe(1000).is_ok());
+        assert!(solver.probe(1000).is_ok());
+        let resolvents: Vec<&[Literal]> = solver
+            .learned_clauses
+            .iter()
+            .map(|clause| solver.clauses.literals(*clause))
+            .collect();
+        assert_eq!(vec![&[Literal::from(-2), Literal::from(5)][..]], resolvents);
+        assert_eq!(1, solver.stats.hyper_binary_resolvents);
+    }
+
+    #[test]
+    fn probe_learns_literals_implied_by_both_polarities() {
+        let mut solver = new_solver();
+        solver.add_clause(vec![-1, 2]);
+        solver.add_clause(vec![-1, -2, 3]);
+        solver.add_clause(vec![1, 3, 4]);
+        solver.add_clause(vec![-4]);
+        assert!(solver.unit_propagate().is_none());
+
+        assert!(solver.probe(1000).is_ok());
+        assert_eq!(Some(true), solver.assigns[3]);
+        assert_eq!(None, solver.assigns[1]);
+        assert_eq!(1, solver.stats.failed_literals);
+    }
+}
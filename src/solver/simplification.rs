@@ -0,0 +1,95 @@
+use clause::ClauseRef;
+use literal::Literal;
+
+use super::Solver;
+
+impl Solver {
+    /// Removes the irredundant and learned clauses that are satisfied on
+    /// decision level 0 and the literals that are false on decision level 0
+    /// from all other clauses, if there are new units since the last time.
+    /// Shortened clauses are replaced, so they are watched like new clauses,
+    /// binary ones in the binary watch lists.
+    ///
+    /// Runs on decision level 0 after propagation, where an unsatisfied
+    /// clause watches two unassigned literals, so no clause becomes unit.
+    pub(super) fn simplify_clauses(&mut self) -> Result<(), ()> {
+        if self.trail.len() == self.simplified_units {
+            return Ok(());
+        }
+        self.stats.simplification_time.start();
+        let result = self.remove_assigned_literals();
+        self.stats.simplification_time.end();
+        self.simplified_units = self.trail.len();
+        result
+    }
+
+    fn remove_assigned_literals(&mut self) -> Result<(), ()> {
+        let assigned: Vec<ClauseRef> = self
+            .clauses
+            .iter()
+            .filter(|clause| {
+                self.clauses
+                    .literals(*clause)
+                    .iter()
+                    .any(|literal| self.assigns[literal.var()].is_some())
+            })
+            .collect();
+        for clause in assigned {
+            if self.is_satisfied(clause) {
+                self.remove_clause(clause);
+                self.stats.satisfied_clauses += 1;
+                continue;
+            }
+            let literals: Vec<Literal> = self
+                .clauses
+                .literals(clause)
+                .iter()
+                .cloned()
+                .filter(|literal| self.assigns[literal.var()].is_none())
+                .collect();
+            self.stats.falsified_literals += self.clauses.len(clause) - literals.len();
+            self.replace_clause(clause, literals)?;
+        }
+
+        self.purge_deleted_clauses();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::new_solver;
+    use super::*;
+
+    #[test]
+    fn simplify_clauses_removes_satisfied_clauses_and_false_literals() {
+        let mut solver = new_solver();
+        solver.add_clause(vec![1]);
+        solver.add_clause(vec![1, 2, 3]);
+        solver.add_clause(vec![-1, 2, 3]);
+        solver.add_clause(vec![-1, 2, 3, 4]);
+        let learned = solver
+            .clauses
+            .add(&[Literal::from(1), Literal::from(4)], true);
+        solver.attach_clause(learned);
+        solver.learned_clauses.push(learned);
+        assert!(solver.unit_propagate().is_none());
+
+        assert_eq!(Ok(()), solver.simplify_clauses());
+        assert_eq!(3, solver.stats.satisfied_clauses);
+        assert_eq!(2, solver.stats.falsified_literals);
+        let clauses: Vec<&[Literal]> = solver
+            .clauses
+            .iter()
+            .map(|clause| solver.clauses.literals(clause))
+            .collect();
+        let literals = |numbers: &[i64]| numbers.iter().map(Literal::new).collect::<Vec<_>>();
+        assert_eq!(
+            vec![&literals(&[2, 3])[..], &literals(&[2, 3, 4])[..]],
+            clauses
+        );
+        assert!(solver.learned_clauses.is_empty());
+        assert_eq!(1, solver.binary_watches[Literal::from(2).index()].len());
+        assert!(solver.watches[Literal::from(-1).index()].is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+use clause::ClauseRef;
+use literal::Literal;
+
+use super::Solver;
+
+impl Solver {
+    /// Replaces literals that are equivalent through binary clauses by a
+    /// representative of their equivalence class. Clauses that become
+    /// tautologies are removed, and the substituted variables get the value
+    /// of their representative in the model.
+    ///
+    /// The equivalence classes are the strongly connected components of the
+    /// binary implication graph. The search is linear in its size, so it has
+    /// no budget; the returned effort is the number of its edges plus the
+    /// literals of the rewritten clauses.
+    pub(super) fn substitute_equivalences(&mut self) -> Result<usize, ()> {
+        let mut spent = self.binary_watches.iter().map(Vec::len).sum();
+        let representatives = self.equivalences()?;
+        let mut substituted = 0;
+        for var in 1..self.assigns.len() {
+            let positive = Literal::from_var(var, true);
+            let representative = representatives[positive.index()];
+            if representative != positive {
+                self.reconstruction
+                    .push(!positive, &[!positive, representative]);
+                self.reconstruction
+                    .push(positive, &[positive, !representative]);
+                self.eliminated[var] = true;
+                self.decision_provider.set_decision(var, false);
+                substituted += 1;
+            }
+        }
+        if substituted == 0 {
+            return Ok(spent);
+        }
+        self.stats.substituted_variables += substituted;
+
+        let clauses: Vec<ClauseRef> = self
+            .clauses
+            .iter()
+            .filter(|clause| {
+                self.clauses
+                    .literals(*clause)
+                    .iter()
+                    .any(|literal| representatives[literal.index()] != *literal)
+            })
+            .collect();
+        for clause in clauses {
+            spent += self.clauses.len(clause);
+            let mut literals: Vec<Literal> = self
+                .clauses
+                .literals(clause)
+                .iter()
+                .map(|literal| representatives[literal.index()])
+                .collect();
+            literals.sort();
+            literals.dedup();
+            if literals.windows(2).any(|pair| pair[0] == !pair[1]) {
+                self.remove_clause(clause);
+            } else {
+                self.replace_clause(clause, literals)?;
+            }
+        }
+
+        self.purge_deleted_clauses();
+        Ok(spent)
+    }
+
+    /// For each literal, the representative of its strongly connected
+    /// component in the binary implication graph of the unassigned literals,
+    /// or an error if a literal is equivalent to its negation.
+    ///
+    /// A component and its negation get negated representatives. Frozen
+    /// variables are only substituted by themselves.
+    fn equivalences(&self) -> Result<Vec<Literal>, ()> {
+        let literals = self.watches.len();
+        let mut representatives: Vec<Literal> = (0..literals).map(Literal::from_index).collect();
+        // Tarjan's algorithm, with 0 for literals that were not visited yet
+        let mut index = vec![0; literals];
+        let mut lowlink = vec![0; literals];
+        let mut on_stack = vec![false; literals];
+        let mut stack = Vec::new();
+        let mut visited = 0;
+        for start in 2..literals {
+            if index[start] != 0 || self.assigns[Literal::from_index(start).var()].is_some() {
+                continue;
+            }
+            visited += 1;
+            index[start] = visited;
+            lowlink[start] = visited;
+            stack.push(start);
+            on_stack[start] = true;
+            // the visited literals with the position of their next successor
+            let mut calls = vec![(start, 0)];
+            while let Some(&(node, edge)) = calls.last() {
+                let successors = &self.binary_watches[(!Literal::from_index(node)).index()];
+                if edge < successors.len() {
+                    calls.last_mut().expect("Empty call stack").1 += 1;
+                    let successor = successors[edge].blocker;
+                    let next = successor.index();
+                    if self.assigns[successor.var()].is_some() {
+                        continue;
+                    }
+                    if index[next] == 0 {
+                        visited += 1;
+                        index[next] = visited;
+                        lowlink[next] = visited;
+                        stack.push(next);
+                        on_stack[next] = true;
+                        calls.push((next, 0));
+                    } else if on_stack[next] {
+                        lowlink[node] = lowlink[node].min(index[next]);
+                    }
+                    continue;
+                }
+
+                calls.pop();
+                if let Some(&(parent, _)) = calls.last() {
+                    lowlink[parent] = lowlink[parent].min(lowlink[node]);
+                }
+                if lowlink[node] != index[node] {
+                    continue;
+                }
+                let mut component = Vec::new();
+                loop {
+                    let member = stack.pop().expect("Empty component stack");
+                    on_stack[member] = false;
+                    component.push(Literal::from_index(member));
+                    if member == node {
+                        break;
+                    }
+                }
+                // the negated component was found before
+                if component.len() == 1 || representatives[node] != Literal::from_index(node) {
+                    continue;
+                }
+                component.sort();
+                if component.windows(2).any(|pair| pair[0] == !pair[1]) {
+                    return Err(());
+                }
+                let representative = *component
+                    .iter()
+                    .find(|literal| self.frozen[literal.var()])
+                    .unwrap_or(&component[0]);
+                for literal in component {
+                    if !self.frozen[literal.var()] {
+                        representatives[literal.index()] = representative;
+                        representatives[(!literal).index()] = !representative;
+                    }
+                }
+            }
+        }
+        Ok(representatives)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::super::tests::new_solver;
+    use super::*;
+    use decision_provider::DecisionProvider;
+
+    /// Counts the clauses reported to it.
+    #[derive(Debug, Default)]
+    struct ClauseCounter {
+        clauses: Arc<AtomicUsize>,
+    }
+
+    impl DecisionProvider for ClauseCounter {
+        fn new_clause(&mut self, _literals: &[Literal]) {
+            self.clauses.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn assign(&mut self, _literal: Literal) {}
+
+        fn unassign(&mut self, _literal: Literal) {}
+
+        fn get_next(&mut self) -> Option<Literal> {
+            None
+        }
+    }
+
+    #[test]
+    fn substitute_equivalences_replaces_equivalent_literals() {
+        let mut solver = new_solver();
+        // 1 = -2 = 3
+        solver.add_clause(vec![-1, -2]);
+        solver.add_clause(vec![2, 3]);
+        solver.add_clause(vec![-3, 1]);
+        solver.add_clause(vec![1, 4, 5]);
+        solver.add_clause(vec![-2, 3, 5]);
+        solver.add_clause(vec![2, 4, 6]);
+
+        assert!(solver.substitute_equivalences().is_ok());
+        assert_eq!(2, solver.stats.substituted_variables);
+        let clauses: Vec<&[Literal]> = solver
+            .clauses
+            .iter()
+            .map(|clause| solver.clauses.literals(clause))
+            .collect();
+        let literals = |numbers: &[i64]| numbers.iter().map(Literal::new).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                &literals(&[1, 4, 5])[..],
+                &literals(&[1, 5])[..],
+                &literals(&[-1, 4, 6])[..]
+            ],
+            clauses
+        );
+
+        let mut model = vec![None, Some(false), None, None, Some(true), None];
+        solver.reconstruction.extend(&mut model);
+        assert_eq!(vec![Some(true), Some(false)], model[2..4].to_vec());
+    }
+
+    #[test]
+    fn substitute_equivalences_finds_literal_equivalent_to_its_negation() {
+        let mut solver = new_solver();
+        solver.add_clause(vec![-1, 2]);
+        solver.add_clause(vec![-2, -1]);
+        solver.add_clause(vec![1, 3]);
+        solver.add_clause(vec![-3, 1]);
+        assert_eq!(Err(()), solver.substitute_equivalences());
+    }
+
+    #[test]
+    fn substitute_equivalences_does_not_report_replaced_clauses() {
+        let counter = ClauseCounter::default();
+        let clauses = counter.clauses.clone();
+        let mut solver = Solver::new(Box::new(counter));
+        solver.ensure_vars(4);
+        solver.add_clause(vec![-1, 2]);
+        solver.add_clause(vec![-2, 1]);
+        solver.add_clause(vec![2, 3, 4]);
+        assert_eq!(3, clauses.load(Ordering::SeqCst));
+
+        assert!(solver.substitute_equivalences().is_ok());
+        assert_eq!(1, solver.stats.substituted_variables);
+        assert_eq!(3, clauses.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use clause::ClauseRef;
+use literal::Literal;
+
+use super::Solver;
+
+/// How a clause relates to a clause it is compared with.
+#[derive(Debug, Eq, PartialEq)]
+enum Subsumption {
+    /// The other clause contains all literals of the clause.
+    Subsumes,
+    /// The other clause contains all literals of the clause except one, and
+    /// the negation of that one, which can be removed from it.
+    Strengthens(Literal),
+}
+
+impl Solver {
+    // clauses longer than this are not used to subsume other clauses
+    const SUBSUMPTION_CLAUSE_SIZE: usize = 100;
+
+    /// Removes clauses that contain all literals of another clause, and
+    /// removes a literal from a clause if another clause contains its
+    /// negation and otherwise only literals of the clause (self-subsuming
+    /// resolution). Both irredundant and learned clauses take part; a
+    /// learned clause that subsumes an irredundant one becomes irredundant.
+    ///
+    /// Stops after looking at about `steps` literals of compared clauses and
+    /// returns how many it looked at.
+    ///
+    /// http://fmv.jku.at/papers/EenBiere-SAT05.pdf
+    pub(super) fn subsume_clauses(&mut self, steps: usize) -> Result<usize, ()> {
+        let tautologies: Vec<ClauseRef> = self
+            .clauses
+            .iter()
+            .filter(|clause| self.is_tautology(*clause))
+            .collect();
+        for clause in tautologies {
+            self.remove_clause(clause);
+        }
+
+        let mut occurrences = self.occurrence_lists(true);
+        let mut queue: Vec<ClauseRef> = self
+            .clauses
+            .iter()
+            .filter(|clause| {
+                self.clauses.len(*clause) <= Solver::SUBSUMPTION_CLAUSE_SIZE
+                    && !self.is_satisfied(*clause)
+            })
+            .collect();
+        queue.sort_by_key(|clause| self.clauses.len(*clause));
+        let mut signatures = HashMap::new();
+        let mut marks = vec![false; self.watches.len()];
+        let mut spent = 0;
+        let mut next = 0;
+        while next < queue.len() && spent < steps {
+            let clause = queue[next];
+            next += 1;
+            if self.clauses.is_deleted(clause) {
+                continue;
+            }
+            let literals = self.clauses.literals(clause).to_vec();
+            // compare with the clauses containing the least frequent variable
+            let pivot = *literals
+                .iter()
+                .min_by_key(|literal| {
+                    occurrences[literal.index()].len() + occurrences[(!**literal).index()].len()
+                })
+                .expect("Empty clause in database");
+            let others: Vec<ClauseRef> = occurrences[pivot.index()]
+                .iter()
+                .chain(occurrences[(!pivot).index()].iter())
+                .cloned()
+                .collect();
+            let signature = self.signature(clause);
+            for literal in literals.iter() {
+                marks[literal.index()] = true;
+            }
+            for other in others {
+                if other == clause
+                    || self.clauses.is_deleted(other)
+                    || self.clauses.len(other) < literals.len()
+                    || signature
+                        & !*signatures
+                            .entry(other)
+                            .or_insert_with(|| self.signature(other))
+                        != 0
+                {
+                    continue;
+                }
+                spent += self.clauses.len(other);
+                match self.subsumption(&marks, literals.len(), other) {
+                    Some(Subsumption::Subsumes) => {
+                        if self.clauses.is_learned(clause) && !self.clauses.is_learned(other) {
+                            self.clauses.set_learned(clause, false);
+                        }
+                        self.remove_clause(other);
+                        self.stats.subsumed_clauses += 1;
+                    }
+                    Some(Subsumption::Strengthens(literal)) => {
+                        self.stats.strengthened_clauses += 1;
+                        let literals = self
+                            .clauses
+                            .literals(other)
+                            .iter()
+                            .cloned()
+                            .filter(|other| *other != literal)
+                            .collect();
+                        if let Some(strengthened) = self.replace_clause(other, literals)? {
+                            for literal in self.clauses.literals(strengthened) {
+                                occurrences[literal.index()].push(strengthened);
+                            }
+                            queue.push(strengthened);
+                        }
+                    }
+                    None => {}
+                }
+            }
+            for literal in literals.iter() {
+                marks[literal.index()] = false;
+            }
+        }
+
+        // learned clauses that subsume irredundant ones became irredundant
+        let clauses = &self.clauses;
+        self.learned_clauses
+            .retain(|clause| clauses.is_learned(*clause));
+        self.purge_deleted_clauses();
+        Ok(spent)
+    }
+
+    /// Whether the marked literals, `size` many, subsume or strengthen
+    /// `other`.
+    fn subsumption(&self, marks: &[bool], size: usize, other: ClauseRef) -> Option<Subsumption> {
+        let mut common = 0;
+        let mut negated = None;
+        for literal in self.clauses.literals(other) {
+            if marks[literal.index()] {
+                common += 1;
+            } else if marks[(!*literal).index()] {
+                if negated.is_some() {
+                    return None;
+                }
+                negated = Some(*literal);
+            }
+        }
+        match negated {
+            None if common == size => Some(Subsumption::Subsumes),
+            Some(literal) if common + 1 == size => Some(Subsumption::Strengthens(literal)),
+            _ => None,
+        }
+    }
+
+    /// A bit set with one of 64 bits set for every variable of `clause`.
+    /// A clause can only subsume another if its bits are a subset.
+    fn signature(&self, clause: ClauseRef) -> u64 {
+        self.clauses
+            .literals(clause)
+            .iter()
+            .fold(0, |signature, literal| {
+                signature | 1 << (literal.var() & 63)
+            })
+    }
+
+    fn is_tautology(&self, clause: ClauseRef) -> bool {
+        let literals = self.clauses.literals(clause);
+        literals.iter().any(|literal| literals.contains(&!*literal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::new_solver;
+    use super::*;
+
+    fn literals(solver: &Solver) -> Vec<Vec<i64>> {
+        let mut clauses: Vec<Vec<i64>> = solver
+            .clauses
+            .iter()
+            .map(|clause| {
+                let mut literals: Vec<i64> = solver
+                    .clauses
+                    .literals(clause)
+                    .iter()
+                    .map(Literal::to_dimacs)
+                    .collect();
+                literals.sort();
+                literals
+            })
+            .collect();
+        clauses.sort();
+        clauses
+    }
+
+    #[test]
+    fn subsume_clauses_removes_subsumed_and_strengthens_clauses() {
+        let mut solver = new_solver();
+        solver.add_clause(vec![1, 2]);
+        solver.add_clause(vec![1, 2, 3]);
+        solver.add_clause(vec![-1, 2, 4]);
+        solver.add_clause(vec![5, -5, 6]);
+        solver.add_clause(vec![7, 8, 9]);
+        let learned = solver
+            .clauses
+            .add(&[Literal::from(7), Literal::from(8)], true);
+        solver.attach_clause(learned);
+        solver.learned_clauses.push(learned);
+
+        assert!(solver.subsume_clauses(1000).is_ok());
+        assert_eq!(vec![vec![1, 2], vec![2, 4], vec![7, 8]], literals(&solver));
+        assert_eq!(2, solver.stats.subsumed_clauses);
+        assert_eq!(1, solver.stats.strengthened_clauses);
+        assert!(solver.learned_clauses.is_empty());
+    }
+}
@@ -0,0 +1,238 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use clause::ClauseRef;
+use literal::Literal;
+
+use super::Solver;
+
+/// A clause containing the literal that is being replaced, with the clauses
+/// that contain one of the other matched literals instead, in the order of
+/// the matched literals.
+struct Match {
+    clause: ClauseRef,
+    partners: Vec<ClauseRef>,
+}
+
+impl Solver {
+    // literals of compared clauses looked at in one call of `add_variables`
+    const VARIABLE_ADDITION_STEPS: usize = 10_000_000;
+    // longer clauses are not replaced
+    const VARIABLE_ADDITION_CLAUSE_SIZE: usize = 100;
+
+    /// Bounded variable addition: finds a set of literals `L` and a set of
+    /// clauses `R` such that the formula contains `l ∨ r` for every `l` in
+    /// `L` and `r` in `R`, and replaces these `|L| * |R|` clauses by
+    /// `l ∨ x` for every `l` and `r ∨ ¬x` for every `r`, with a fresh
+    /// variable `x`, if that makes the formula smaller. This compresses, for
+    /// example, pairwise at-most-one constraints. Resolving the new clauses
+    /// on `x` gives back the replaced ones, so nothing needs to be
+    /// reconstructed; the fresh variables are left out of the model.
+    ///
+    /// https://doi.org/10.1007/978-3-642-34188-5_14
+    pub(super) fn add_variables(&mut self) -> Result<(), ()> {
+        let mut occurrences = self.occurrence_lists(false);
+        let mut queue: BinaryHeap<(usize, Literal)> = (2..occurrences.len())
+            .map(Literal::from_index)
+            .map(|literal| (occurrences[literal.index()].len(), literal))
+            .filter(|&(count, _)| count > 2)
+            .collect();
+        let mut marks = vec![false; occurrences.len()];
+        let mut steps = 0;
+        while let Some((count, literal)) = queue.pop() {
+            if steps >= Solver::VARIABLE_ADDITION_STEPS {
+                break;
+            }
+            let clauses: Vec<ClauseRef> = self
+                .live_occurrences(literal, &occurrences)
+                .into_iter()
+                .filter(|clause| self.clauses.len(*clause) <= Solver::VARIABLE_ADDITION_CLAUSE_SIZE)
+                .collect();
+            if clauses.len() < count {
+                // the count in the queue is out of date
+                if clauses.len() > 2 {
+                    queue.push((clauses.len(), literal));
+                }
+                continue;
+            }
+
+            let mut literals = vec![literal];
+            let mut matches: Vec<Match> = clauses
+                .into_iter()
+                .map(|clause| Match {
+                    clause,
+                    partners: Vec::new(),
+                })
+                .collect();
+            loop {
+                let partners =
+                    self.partners(literal, &matches, &occurrences, &mut marks, &mut steps);
+                let mut counts: HashMap<Literal, usize> = HashMap::new();
+                for &(partner, _, _) in partners.iter() {
+                    if !literals.contains(&partner) {
+                        *counts.entry(partner).or_insert(0) += 1;
+                    }
+                }
+                let best = match counts
+                    .into_iter()
+                    .max_by_key(|&(partner, count)| (count, Reverse(partner)))
+                {
+                    Some((best, _)) => best,
+                    None => break,
+                };
+                // duplicate matched clauses find the same partners
+                let mut taken = HashSet::new();
+                let extended: Vec<Match> = partners
+                    .into_iter()
+                    .filter(|&(partner, _, clause)| partner == best && taken.insert(clause))
+                    .map(|(_, index, partner)| {
+                        let mut extended = Match {
+                            clause: matches[index].clause,
+                            partners: matches[index].partners.clone(),
+                        };
+                        extended.partners.push(partner);
+                        extended
+                    })
+                    .collect();
+                if reduction(literals.len() + 1, extended.len())
+                    <= reduction(literals.len(), matches.len())
+                {
+                    break;
+                }
+                literals.push(best);
+                matches = extended;
+            }
+            if reduction(literals.len(), matches.len()) <= 0 {
+                continue;
+            }
+
+            let var = self.assigns.len();
+            self.ensure_vars(var);
+            occurrences.resize(self.watches.len(), Vec::new());
+            marks.resize(self.watches.len(), false);
+            self.stats.added_variables += 1;
+            let fresh = Literal::from_var(var, true);
+            let mut added = Vec::new();
+            for literal in literals.iter() {
+                added.push(vec![*literal, fresh]);
+            }
+            for matched in matches.iter() {
+                let mut rest: Vec<Literal> = self
+                    .clauses
+                    .literals(matched.clause)
+                    .iter()
+                    .cloned()
+                    .filter(|other| *other != literal)
+                    .collect();
+                rest.push(!fresh);
+                added.push(rest);
+                self.remove_clause(matched.clause);
+                for partner in matched.partners.iter() {
+                    self.remove_clause(*partner);
+                }
+            }
+            for literals in added {
+                if let Some(clause) = self.add_derived_clause(literals, false)? {
+                    for literal in self.clauses.literals(clause) {
+                        occurrences[literal.index()].push(clause);
+                    }
+                }
+            }
+            let count = self.live_occurrences(literal, &occurrences).len();
+            if count > 2 {
+                queue.push((count, literal));
+            }
+        }
+
+        self.purge_deleted_clauses();
+        Ok(())
+    }
+
+    /// For each matched clause, the literals other than `literal` that it
+    /// could be matched with: the clauses that contain them instead of
+    /// `literal` and otherwise the same literals, as the literal, the index
+    /// of the match and the clause.
+    fn partners(
+        &self,
+        literal: Literal,
+        matches: &[Match],
+        occurrences: &[Vec<ClauseRef>],
+        marks: &mut [bool],
+        steps: &mut usize,
+    ) -> Vec<(Literal, usize, ClauseRef)> {
+        let mut partners = Vec::new();
+        for (index, matched) in matches.iter().enumerate() {
+            let literals = self.clauses.literals(matched.clause);
+            for other in literals.iter().filter(|other| **other != literal) {
+                marks[other.index()] = true;
+            }
+            // the clauses to look at all contain the least frequent other literal
+            let least = literals
+                .iter()
+                .filter(|other| **other != literal)
+                .min_by_key(|other| occurrences[other.index()].len());
+            if let Some(least) = least {
+                let start = partners.len();
+                for other in occurrences[least.index()].iter() {
+                    if self.clauses.is_deleted(*other) || self.clauses.len(*other) != literals.len()
+                    {
+                        continue;
+                    }
+                    *steps += literals.len();
+                    let mut missing = self
+                        .clauses
+                        .literals(*other)
+                        .iter()
+                        .filter(|other| !marks[other.index()]);
+                    if let (Some(partner), None) = (missing.next(), missing.next()) {
+                        let partner = *partner;
+                        if partner != literal
+                            && partner != !literal
+                            && !partners[start..]
+                                .iter()
+                                .any(|&(seen, _, _)| seen == partner)
+                        {
+                            partners.push((partner, index, *other));
+                        }
+                    }
+                }
+            }
+            for other in literals.iter() {
+                marks[other.index()] = false;
+            }
+        }
+        partners
+    }
+}
+
+/// How many clauses fewer there are after replacing the products of
+/// `literals` literals and `clauses` clauses.
+fn reduction(literals: usize, clauses: usize) -> isize {
+    (literals * clauses) as isize - (literals + clauses) as isize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::new_solver;
+    use solver::SolverResult;
+
+    #[test]
+    fn add_variables_compresses_at_most_one_constraints() {
+        let mut solver = new_solver();
+        solver.add_clause((1..7).collect());
+        for i in 1..7 {
+            for j in i + 1..7 {
+                solver.add_clause(vec![-i, -j]);
+            }
+        }
+
+        assert_eq!(Ok(()), solver.add_variables());
+        assert!(solver.stats.added_variables > 0);
+        assert!(solver.clauses.iter().count() < 16);
+
+        assert_eq!(SolverResult::Sat, solver.solve());
+        let model = solver.model();
+        assert_eq!(6, model.len());
+        assert_eq!(1, model.iter().filter(|literal| **literal > 0).count());
+    }
+}
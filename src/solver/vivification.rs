@@ -0,0 +1,135 @@
+use std::cmp::Ordering;
+
+use clause::ClauseRef;
+
+use super::{Solver, Tier};
+
+impl Solver {
+    /// Shortens clauses by assigning the negations of their literals one by
+    /// one and propagating without the clause itself. If that leads to a
+    /// conflict or makes one of the remaining literals true, the literals
+    /// that were not assigned yet are not needed, and literals that become
+    /// false can be left out.
+    ///
+    /// Learned clauses that are not local are vivified first, most active
+    /// first, then irredundant clauses, until about `propagations`
+    /// propagations were made; returns how many.
+    ///
+    /// https://doi.org/10.3233/978-1-58603-891-5-525
+    pub(super) fn vivify_clauses(&mut self, propagations: usize) -> Result<usize, ()> {
+        let mut candidates: Vec<ClauseRef> = self
+            .learned_clauses
+            .iter()
+            .cloned()
+            .filter(|clause| self.tier(*clause) != Tier::Local)
+            .collect();
+        candidates.sort_by(|a, b| {
+            self.clauses
+                .activity(*b)
+                .partial_cmp(&self.clauses.activity(*a))
+                .unwrap_or(Ordering::Equal)
+        });
+        candidates.extend(
+            self.clauses
+                .iter()
+                .filter(|clause| !self.clauses.is_learned(*clause)),
+        );
+
+        let start = self.stats.propagations;
+        for clause in candidates {
+            if self.stats.propagations - start >= propagations {
+                break;
+            }
+            if self.clauses.is_deleted(clause)
+                || self.clauses.len(clause) <= 2
+                || self.is_satisfied(clause)
+            {
+                continue;
+            }
+            self.vivify_clause(clause)?;
+        }
+
+        self.purge_deleted_clauses();
+        Ok(self.stats.propagations - start)
+    }
+
+    fn vivify_clause(&mut self, clause: ClauseRef) -> Result<(), ()> {
+        let literals = self.clauses.literals(clause).to_vec();
+        self.detach_clause(clause);
+        let mut needed = Vec::new();
+        for literal in literals.iter() {
+            match self.assigns[literal.var()] {
+                Some(value) if value == literal.sign() => {
+                    needed.push(*literal);
+                    break;
+                }
+                Some(_) => {}
+                None => {
+                    needed.push(*literal);
+                    self.store_decision(!*literal)?;
+                    if self.unit_propagate().is_some() {
+                        break;
+                    }
+                }
+            }
+        }
+        if !self.trail_lim.is_empty() {
+            self.backtrack(0);
+        }
+
+        if needed.len() < literals.len() {
+            self.stats.vivified_clauses += 1;
+            self.stats.vivified_literals += literals.len() - needed.len();
+            self.replace_clause(clause, needed)?;
+        } else {
+            self.attach_clause(clause);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::new_solver;
+    use literal::Literal;
+
+    #[test]
+    fn vivify_clauses_removes_literals_that_are_not_needed() {
+        let mut solver = new_solver();
+        solver.add_clause(vec![1, 2, 3, 4]);
+        solver.add_clause(vec![1, 5]);
+        solver.add_clause(vec![-5, 6]);
+        solver.add_clause(vec![-6, 2]);
+        solver.add_clause(vec![-5, -2, 3]);
+
+        // -1 makes 2 true, so 3 and 4 are not needed
+        assert!(solver.vivify_clauses(1000).is_ok());
+        assert_eq!(1, solver.stats.vivified_clauses);
+        assert_eq!(2, solver.stats.vivified_literals);
+        let clauses: Vec<Vec<i64>> = solver
+            .clauses
+            .iter()
+            .map(|clause| {
+                let mut literals: Vec<i64> = solver
+                    .clauses
+                    .literals(clause)
+                    .iter()
+                    .map(Literal::to_dimacs)
+                    .collect();
+                literals.sort();
+                literals
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                vec![1, 5],
+                vec![-5, 6],
+                vec![-6, 2],
+                vec![-5, -2, 3],
+                vec![1, 2]
+            ],
+            clauses
+        );
+        assert!(solver.trail.is_empty());
+    }
+}
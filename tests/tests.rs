@@ -4,8 +4,12 @@ extern crate rustasata;
 
 use std::env;
 use std::fs;
+use std::thread;
 
-use rustasata::parser::{parse, parse_file};
+use rustasata::decision_provider::{DecisionProvider, DefaultDecisionProvider, Polarity};
+use rustasata::literal::Literal;
+use rustasata::parser::{parse, parse_file, Dimacs};
+use rustasata::restart::{Geometric, Glucose, InnerOuter, Luby, RestartPolicy};
 use rustasata::solver::{Solver, SolverResult};
 
 fn setup_logger() -> Result<(), fern::InitError> {
@@ -23,13 +27,63 @@ fn setup_logger() -> Result<(), fern::InitError> {
 fn run_test(str: &str) -> SolverResult {
     let _ = setup_logger();
     let dimacs = parse(str).unwrap();
-    Solver::from_dimacs(&dimacs).solve()
+    run_solver(&dimacs)
 }
 
 fn run_test_file(str: &str) -> SolverResult {
     let _ = setup_logger();
     let dimacs = parse_file(str).unwrap();
-    Solver::from_dimacs(&dimacs).solve()
+    run_solver(&dimacs)
+}
+
+fn run_solver(dimacs: &Dimacs) -> SolverResult {
+    let mut solver = Solver::from_dimacs(dimacs);
+    let result = solver.solve();
+    if result == SolverResult::Sat {
+        assert_model(dimacs, &solver.model());
+    }
+    result
+}
+
+fn assert_model(dimacs: &Dimacs, model: &[i64]) {
+    for clause in dimacs.clauses.iter() {
+        assert!(
+            clause.iter().any(|literal| model.contains(literal)),
+            "clause {:?} not satisfied by model {:?}",
+            clause,
+            model
+        );
+    }
+}
+
+/// Branches on the smallest unassigned variable, always negatively.
+#[derive(Debug, Default)]
+struct LowestFirst {
+    assigned: Vec<bool>,
+}
+
+impl DecisionProvider for LowestFirst {
+    fn new_clause(&mut self, literals: &[Literal]) {
+        for literal in literals {
+            if literal.var() >= self.assigned.len() {
+                self.assigned.resize(literal.var() + 1, false);
+            }
+        }
+    }
+
+    fn assign(&mut self, literal: Literal) {
+        self.assigned[literal.var()] = true;
+    }
+
+    fn unassign(&mut self, literal: Literal) {
+        self.assigned[literal.var()] = false;
+    }
+
+    fn get_next(&mut self) -> Option<Literal> {
+        (1..self.assigned.len())
+            .find(|&var| !self.assigned[var])
+            .map(|var| Literal::from_var(var, false))
+    }
 }
 
 #[test]
@@ -151,6 +205,213 @@ fn test_tiny_sat_instance_4() {
     assert_eq!(result, SolverResult::Sat);
 }
 
+#[test]
+fn test_custom_decision_provider() {
+    let _ = setup_logger();
+    let dimacs = parse(
+        "
+        1 2 3
+        1 2 -3
+        1 -2 3
+        1 -2 -3
+        -1 4
+    ",
+    )
+    .unwrap();
+    let mut solver = Solver::with_decision_provider(&dimacs, Box::new(LowestFirst::default()));
+    assert_eq!(solver.solve(), SolverResult::Sat);
+}
+
+#[test]
+fn test_polarities() {
+    let _ = setup_logger();
+    let sat = parse("1 2 3\n-1 -2\n-2 -3\n-1 -3\n2 3").unwrap();
+    let unsat = parse("1 2\n-1 2\n1 -2\n-1 -2").unwrap();
+    let polarities = || {
+        vec![
+            Polarity::Saved,
+            Polarity::Occurrences,
+            Polarity::False,
+            Polarity::True,
+            Polarity::Random(42),
+            Polarity::JeroslowWang,
+            Polarity::Custom(Box::new(|var| var % 2 == 0)),
+        ]
+    };
+    for polarity in polarities() {
+        let provider = Box::new(DefaultDecisionProvider::with_polarity(polarity));
+        let mut solver = Solver::with_decision_provider(&sat, provider);
+        assert_eq!(solver.solve(), SolverResult::Sat);
+    }
+    for polarity in polarities() {
+        let provider = Box::new(DefaultDecisionProvider::with_polarity(polarity));
+        let mut solver = Solver::with_decision_provider(&unsat, provider);
+        assert_eq!(solver.solve(), SolverResult::Unsat);
+    }
+}
+
+#[test]
+fn test_warm_start_from_model() {
+    let _ = setup_logger();
+    let dimacs = parse_file("test/trivial/random.txt").unwrap();
+    let mut solver = Solver::from_dimacs(&dimacs);
+    assert_eq!(solver.solve(), SolverResult::Sat);
+    let model = solver.model();
+    assert_model(&dimacs, &model);
+
+    let mut solver = Solver::from_dimacs(&dimacs);
+    for literal in model.iter() {
+        solver.set_polarity(literal.unsigned_abs() as usize, *literal > 0);
+    }
+    assert_eq!(solver.solve(), SolverResult::Sat);
+    assert_eq!(model, solver.model());
+}
+
+#[test]
+fn test_non_decision_variables() {
+    let _ = setup_logger();
+    let dimacs = parse("1 2 3\n-3 4").unwrap();
+    let mut solver = Solver::from_dimacs(&dimacs);
+    solver.set_priority(1, 1);
+    solver.set_polarity(1, true);
+    solver.set_decision_var(2, false);
+    solver.set_decision_var(3, false);
+    assert_eq!(solver.solve(), SolverResult::Sat);
+    let model = solver.model();
+    assert!(!model
+        .iter()
+        .any(|literal| literal.abs() == 2 || literal.abs() == 3));
+    assert!(model.contains(&1));
+}
+
+#[test]
+fn test_restart_policies() {
+    let _ = setup_logger();
+    let dimacs = parse_file("test/easy/aim-100-1_6-no-1.txt").unwrap();
+    let policies: Vec<Box<dyn RestartPolicy>> = vec![
+        Box::new(Luby::new(1)),
+        Box::new(Geometric::new(1, 1.5)),
+        Box::new(InnerOuter::new(1, 1.1)),
+        Box::new(Glucose::new(1.0)),
+        Box::new(Glucose::with_blocking(1.0, 1.0)),
+    ];
+    for policy in policies {
+        let mut solver = Solver::from_dimacs(&dimacs);
+        solver.set_restart_policy(policy);
+        assert_eq!(solver.solve(), SolverResult::Unsat);
+    }
+}
+
+#[test]
+fn test_mode_switching() {
+    let _ = setup_logger();
+    let sat = parse_file("test/hard/flat200-43.txt").unwrap();
+    let unsat = parse_file("test/hard/bf0432-007.txt").unwrap();
+    for &mode_switching in [true, false].iter() {
+        let mut solver = Solver::from_dimacs(&sat);
+        solver.set_mode_switching(mode_switching);
+        solver.set_stable_restart_policy(Box::new(Luby::new(4)));
+        assert_eq!(solver.solve(), SolverResult::Sat);
+        assert_model(&sat, &solver.model());
+
+        let mut solver = Solver::from_dimacs(&unsat);
+        solver.set_mode_switching(mode_switching);
+        assert_eq!(solver.solve(), SolverResult::Unsat);
+    }
+}
+
+#[test]
+fn test_trail_reuse() {
+    let _ = setup_logger();
+    let sat = parse_file("test/hard/flat200-43.txt").unwrap();
+    let unsat = parse_file("test/easy/aim-100-1_6-no-1.txt").unwrap();
+    for &(dimacs, expected) in [(&sat, true), (&unsat, false)].iter() {
+        let mut solver = Solver::from_dimacs(dimacs);
+        solver.set_trail_reuse(true);
+        solver.set_restart_policy(Box::new(Luby::new(1)));
+        assert_eq!(expected, solver.solve() == SolverResult::Sat);
+        if expected {
+            assert_model(dimacs, &solver.model());
+        }
+    }
+}
+
+#[test]
+fn test_dimacs_header() {
+    let dimacs = parse("c comment\np cnf 5 2\n1 -2 0\n2 3 0").unwrap();
+    assert_eq!(dimacs.variables(), 5);
+    assert_eq!(dimacs.clauses, vec![vec![1, -2], vec![2, 3]]);
+    assert_eq!(parse("1 -7 0").unwrap().variables(), 7);
+    assert_eq!(parse("p cnf 2 1\n1 -7 0").unwrap().variables(), 7);
+    assert_eq!(Dimacs::new(vec![vec![1, -2]]).variables(), 2);
+    assert!(parse("p cnf x 2\n1 0").is_err());
+    assert!(parse("p cnf 2147483647 1\n1 0").is_ok());
+    assert!(parse("p cnf 2147483648 1\n1 0").is_err());
+    assert!(parse("1 -2147483648 0").is_err());
+}
+
+#[test]
+fn test_variable_elimination() {
+    // eliminating 2 yields the unit -3, which makes the resolvent 3 5 unit
+    let result = run_test(
+        "
+        -3 -2
+        -3 -1
+        4
+        3 -5
+        -1 1 2
+        2 -3
+        -2 5 -4
+        5 1
+        1 -5 3
+        -4 3 2 5
+    ",
+    );
+    assert_eq!(result, SolverResult::Unsat);
+
+    let dimacs = parse_file("test/easy/flat200-89.txt").unwrap();
+    for &elimination in [true, false].iter() {
+        let mut solver = Solver::from_dimacs(&dimacs);
+        solver.set_elimination(elimination);
+        assert_eq!(solver.solve(), SolverResult::Sat);
+        assert_model(&dimacs, &solver.model());
+    }
+}
+
+#[test]
+fn test_variable_addition() {
+    let dimacs = parse_file("test/easy/19x19queens.txt").unwrap();
+    for &variable_addition in [true, false].iter() {
+        let mut solver = Solver::from_dimacs(&dimacs);
+        solver.set_variable_addition(variable_addition);
+        assert_eq!(solver.solve(), SolverResult::Sat);
+        let model = solver.model();
+        assert!(model
+            .iter()
+            .all(|literal| literal.unsigned_abs() as usize <= dimacs.variables()));
+        assert_model(&dimacs, &model);
+    }
+}
+
+#[test]
+fn test_chronological_backtracking() {
+    let dimacs = parse_file("test/hard/flat200-43.txt").unwrap();
+    for &chronological_backtracking in [true, false].iter() {
+        let mut solver = Solver::from_dimacs(&dimacs);
+        solver.set_chronological_backtracking(chronological_backtracking);
+        assert_eq!(solver.solve(), SolverResult::Sat);
+        assert_model(&dimacs, &solver.model());
+    }
+}
+
+#[test]
+fn test_solve_on_other_thread() {
+    let dimacs = parse_file("test/easy/aim-100-1_6-no-1.txt").unwrap();
+    let mut solver = Solver::from_dimacs(&dimacs);
+    let result = thread::spawn(move || solver.solve()).join().unwrap();
+    assert_eq!(result, SolverResult::Unsat);
+}
+
 #[test]
 #[ignore]
 fn test_file_trivial_1() {